@@ -1,3 +1,8 @@
+pub mod bounds;
+pub mod chamber;
 pub mod elf;
+pub mod expression;
+pub mod intervals;
 pub mod point;
-pub mod vertex;
\ No newline at end of file
+pub mod snafu;
+pub mod vertex;
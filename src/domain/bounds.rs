@@ -0,0 +1,203 @@
+use std::{cmp, fmt};
+
+use super::{point::Point, vertex::Vertex};
+
+/// Inclusive axis-aligned rectangle over grid `Point`s.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Bounds {
+    pub min: Point,
+    pub max: Point
+}
+
+impl Bounds {
+    pub fn new(min: Point, max: Point) -> Self {
+        return Bounds { min, max };
+    }
+
+    /// Smallest bounds containing every point, or `None` for an empty iterator.
+    pub fn from_points<I: IntoIterator<Item = Point>>(points: I) -> Option<Self> {
+        let mut iter = points.into_iter();
+        let first = iter.next()?;
+
+        return Some(iter.fold(Bounds::new(first, first), |bounds, p| bounds.include(p)));
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        return point.x >= self.min.x && point.x <= self.max.x
+            && point.y >= self.min.y && point.y <= self.max.y;
+    }
+
+    /// Grows the bounds by `amount` on every side.
+    pub fn expand(&self, amount: i32) -> Self {
+        return Bounds {
+            min: self.min - Point::new(amount, amount),
+            max: self.max + Point::new(amount, amount)
+        };
+    }
+
+    /// Grows the bounds just enough to contain `point`.
+    pub fn include(&self, point: Point) -> Self {
+        return Bounds {
+            min: Point::new(cmp::min(self.min.x, point.x), cmp::min(self.min.y, point.y)),
+            max: Point::new(cmp::max(self.max.x, point.x), cmp::max(self.max.y, point.y))
+        };
+    }
+
+    pub fn width(&self) -> i32 {
+        return self.max.x - self.min.x + 1;
+    }
+
+    pub fn height(&self) -> i32 {
+        return self.max.y - self.min.y + 1;
+    }
+
+    pub fn area(&self) -> i64 {
+        return self.width() as i64 * self.height() as i64;
+    }
+
+    pub fn intersection(&self, other: &Bounds) -> Option<Self> {
+        let min = Point::new(cmp::max(self.min.x, other.min.x), cmp::max(self.min.y, other.min.y));
+        let max = Point::new(cmp::min(self.max.x, other.max.x), cmp::min(self.max.y, other.max.y));
+
+        if min.x > max.x || min.y > max.y {
+            return None;
+        }
+
+        return Some(Bounds { min, max });
+    }
+
+    pub fn union(&self, other: &Bounds) -> Self {
+        return self.include(other.min).include(other.max);
+    }
+
+    /// Every point inside the bounds, row by row from `min.y` to `max.y`.
+    pub fn points(&self) -> impl Iterator<Item = Point> {
+        let bounds = *self;
+        return (bounds.min.y..=bounds.max.y)
+            .flat_map(move |y| (bounds.min.x..=bounds.max.x).map(move |x| Point::new(x, y)));
+    }
+}
+
+impl fmt::Display for Bounds {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}) -> ({})", self.min, self.max)
+    }
+}
+
+/// Inclusive axis-aligned box over `Vertex` coordinates.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Bounds3D {
+    pub min: Vertex,
+    pub max: Vertex
+}
+
+impl Bounds3D {
+    pub fn new(min: Vertex, max: Vertex) -> Self {
+        return Bounds3D { min, max };
+    }
+
+    /// Smallest box containing every vertex, or `None` for an empty iterator.
+    pub fn from_points<I: IntoIterator<Item = Vertex>>(points: I) -> Option<Self> {
+        let mut iter = points.into_iter();
+        let first = iter.next()?;
+
+        return Some(iter.fold(Bounds3D::new(first, first), |bounds, p| bounds.include(p)));
+    }
+
+    pub fn contains(&self, point: Vertex) -> bool {
+        return point.x >= self.min.x && point.x <= self.max.x
+            && point.y >= self.min.y && point.y <= self.max.y
+            && point.z >= self.min.z && point.z <= self.max.z;
+    }
+
+    /// Grows the box by `amount` on every side.
+    pub fn expand(&self, amount: i32) -> Self {
+        return Bounds3D {
+            min: self.min - Vertex::new(amount, amount, amount),
+            max: self.max + Vertex::new(amount, amount, amount)
+        };
+    }
+
+    /// Grows the box just enough to contain `point`.
+    pub fn include(&self, point: Vertex) -> Self {
+        return Bounds3D {
+            min: Vertex::new(cmp::min(self.min.x, point.x), cmp::min(self.min.y, point.y), cmp::min(self.min.z, point.z)),
+            max: Vertex::new(cmp::max(self.max.x, point.x), cmp::max(self.max.y, point.y), cmp::max(self.max.z, point.z))
+        };
+    }
+
+    pub fn volume(&self) -> i64 {
+        let size = self.max - self.min;
+        return (size.x as i64 + 1) * (size.y as i64 + 1) * (size.z as i64 + 1);
+    }
+
+    pub fn intersection(&self, other: &Bounds3D) -> Option<Self> {
+        let min = Vertex::new(cmp::max(self.min.x, other.min.x), cmp::max(self.min.y, other.min.y), cmp::max(self.min.z, other.min.z));
+        let max = Vertex::new(cmp::min(self.max.x, other.max.x), cmp::min(self.max.y, other.max.y), cmp::min(self.max.z, other.max.z));
+
+        if min.x > max.x || min.y > max.y || min.z > max.z {
+            return None;
+        }
+
+        return Some(Bounds3D { min, max });
+    }
+
+    pub fn union(&self, other: &Bounds3D) -> Self {
+        return self.include(other.min).include(other.max);
+    }
+
+    /// Every vertex inside the box, x-major.
+    pub fn points(&self) -> impl Iterator<Item = Vertex> {
+        let bounds = *self;
+        return (bounds.min.x..=bounds.max.x).flat_map(move |x| {
+            (bounds.min.y..=bounds.max.y).flat_map(move |y| {
+                (bounds.min.z..=bounds.max.z).map(move |z| Vertex::new(x, y, z))
+            })
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::{point::Point, vertex::Vertex};
+
+    use super::{Bounds, Bounds3D};
+
+    #[test]
+    fn bounds_can_be_built_from_points() {
+        let bounds = Bounds::from_points(vec![Point::new(3, -1), Point::new(-2, 4), Point::new(0, 0)]).unwrap();
+
+        assert_eq!(bounds.min, Point::new(-2, -1));
+        assert_eq!(bounds.max, Point::new(3, 4));
+        assert_eq!(bounds.area(), 36);
+        assert!(Bounds::from_points(Vec::new()).is_none());
+    }
+
+    #[test]
+    fn bounds_can_intersect_and_union() {
+        let a = Bounds::new(Point::new(0, 0), Point::new(4, 4));
+        let b = Bounds::new(Point::new(3, 2), Point::new(6, 8));
+
+        assert_eq!(a.intersection(&b), Some(Bounds::new(Point::new(3, 2), Point::new(4, 4))));
+        assert_eq!(a.union(&b), Bounds::new(Point::new(0, 0), Point::new(6, 8)));
+        assert_eq!(a.intersection(&Bounds::new(Point::new(5, 5), Point::new(6, 6))), None);
+    }
+
+    #[test]
+    fn bounds_iterates_every_contained_point() {
+        let bounds = Bounds::new(Point::new(0, 0), Point::new(1, 2)).expand(1);
+
+        assert_eq!(bounds.points().count() as i64, bounds.area());
+        assert!(bounds.points().all(|p| bounds.contains(p)));
+    }
+
+    #[test]
+    fn bounds_3d_has_volume_and_contains() {
+        let bounds = Bounds3D::from_points(vec![Vertex::new(1, 1, 1), Vertex::new(2, 3, 1)]).unwrap().expand(1);
+
+        assert_eq!(bounds.volume(), 4 * 5 * 3);
+        assert_eq!(bounds.points().count() as i64, bounds.volume());
+        assert!(bounds.contains(Vertex::new(0, 0, 0)));
+        assert!(!bounds.contains(Vertex::new(0, 0, 3)));
+    }
+}
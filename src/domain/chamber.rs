@@ -0,0 +1,375 @@
+//! A tall, narrow chamber that rocks fall into while jets of gas push them sideways.
+//! Rows of settled rock are stored as bitmasks, bit `x` being column `x` from the left wall.
+
+use std::fmt;
+
+use crate::tools::{cycle::RepeatDetector, parse};
+
+const MAX_WIDTH: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChamberError {
+    InvalidCell(char),
+    EmptyShape,
+    InvalidJet(char),
+    NoShapes,
+    NoJets,
+    TooWide(usize),
+    /// The shape at this index doesn't fit between the walls at its spawn position.
+    ShapeDoesNotFit(usize),
+}
+
+impl fmt::Display for ChamberError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChamberError::InvalidCell(c) => write!(f, "shapes are drawn with '#' and '.', found '{}'", c),
+            ChamberError::EmptyShape => write!(f, "a shape needs at least one '#'"),
+            ChamberError::InvalidJet(c) => write!(f, "jets are '<' or '>', found '{}'", c),
+            ChamberError::NoShapes => write!(f, "the chamber needs at least one shape"),
+            ChamberError::NoJets => write!(f, "the chamber needs at least one jet"),
+            ChamberError::TooWide(width) => write!(f, "{} columns is wider than the {} a row can hold", width, MAX_WIDTH),
+            ChamberError::ShapeDoesNotFit(index) => write!(f, "shape {} doesn't fit in the chamber where it spawns", index),
+        }
+    }
+}
+
+/// A rock shape, stored bottom row first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shape {
+    rows: Vec<u64>,
+    width: usize,
+}
+
+impl Shape {
+    /// Parses ASCII art where `#` is rock and `.` is empty, top row first.
+    pub fn parse(art: &str) -> Result<Shape, ChamberError> {
+        let lines: Vec<&str> = art.lines().map(str::trim).filter(|line| !line.is_empty()).collect();
+
+        let mut rows = Vec::with_capacity(lines.len());
+        let mut width = 0;
+        for line in lines.iter().rev() {
+            let mut row = 0;
+            for (x, c) in line.chars().enumerate() {
+                match c {
+                    '#' if x >= MAX_WIDTH => return Err(ChamberError::TooWide(x + 1)),
+                    '#' => {
+                        row |= 1 << x;
+                        width = width.max(x + 1);
+                    }
+                    '.' => {}
+                    _ => return Err(ChamberError::InvalidCell(c)),
+                }
+            }
+            rows.push(row);
+        }
+
+        if width == 0 {
+            return Err(ChamberError::EmptyShape);
+        }
+
+        return Ok(Shape { rows, width });
+    }
+
+    /// Parses shapes separated by blank lines.
+    pub fn parse_list(art: &str) -> Result<Vec<Shape>, ChamberError> {
+        return parse::blocks(art).into_iter().map(Shape::parse).collect();
+    }
+
+    pub fn width(&self) -> usize {
+        return self.width;
+    }
+
+    pub fn height(&self) -> usize {
+        return self.rows.len();
+    }
+
+    /// The `(x, y)` of every rock in the shape, relative to its bottom left corner.
+    pub fn cells(&self) -> Vec<(usize, usize)> {
+        return self.rows
+            .iter()
+            .enumerate()
+            .flat_map(|(y, row)| (0..self.width).filter(move |x| row & (1 << x) != 0).map(move |x| (x, y)))
+            .collect();
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Jet {
+    Left,
+    Right,
+}
+
+impl Jet {
+    pub fn parse_pattern(input: &str) -> Result<Vec<Jet>, ChamberError> {
+        return input
+            .trim()
+            .chars()
+            .map(|c| match c {
+                '<' => Ok(Jet::Left),
+                '>' => Ok(Jet::Right),
+                _ => Err(ChamberError::InvalidJet(c)),
+            })
+            .collect();
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ChamberConfig {
+    pub width: usize,
+    /// Rocks fall in this order, starting over after the last one.
+    pub shapes: Vec<Shape>,
+    /// Pushes applied in this order, one before every step down, starting over after the last one.
+    pub jets: Vec<Jet>,
+    /// Empty columns between the left wall and a new rock.
+    pub spawn_left: usize,
+    /// Empty rows between the top of the tower and a new rock.
+    pub spawn_gap: usize,
+}
+
+/// Where a rock came to rest. `x` and `y` are its bottom left corner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Landing {
+    pub shape: usize,
+    pub x: usize,
+    pub y: usize,
+}
+
+/// Everything that decides how the tower grows from here: the next shape, the next jet
+/// and how far each column's top is below the top of the tower.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct StateKey {
+    pub shape: usize,
+    pub jet: usize,
+    pub profile: Vec<usize>,
+}
+
+pub struct Chamber {
+    config: ChamberConfig,
+    rows: Vec<u64>,
+    column_heights: Vec<usize>,
+    rocks_dropped: u64,
+    next_shape: usize,
+    next_jet: usize,
+}
+
+impl Chamber {
+    pub fn new(config: ChamberConfig) -> Result<Self, ChamberError> {
+        if config.width > MAX_WIDTH {
+            return Err(ChamberError::TooWide(config.width));
+        }
+        if config.shapes.is_empty() {
+            return Err(ChamberError::NoShapes);
+        }
+        if config.jets.is_empty() {
+            return Err(ChamberError::NoJets);
+        }
+        if let Some(index) = config.shapes.iter().position(|shape| config.spawn_left + shape.width > config.width) {
+            return Err(ChamberError::ShapeDoesNotFit(index));
+        }
+
+        let column_heights = vec![0; config.width];
+        return Ok(Chamber { config, rows: Vec::new(), column_heights, rocks_dropped: 0, next_shape: 0, next_jet: 0 });
+    }
+
+    pub fn config(&self) -> &ChamberConfig {
+        return &self.config;
+    }
+
+    pub fn height(&self) -> usize {
+        return self.rows.len();
+    }
+
+    pub fn rocks_dropped(&self) -> u64 {
+        return self.rocks_dropped;
+    }
+
+    /// Whether settled rock fills column `x` of row `y`, counting rows up from the floor.
+    pub fn is_rock(&self, x: usize, y: usize) -> bool {
+        return self.rows.get(y).is_some_and(|row| row & (1 << x) != 0);
+    }
+
+    /// The height of the highest rock in each column.
+    pub fn column_heights(&self) -> &[usize] {
+        return &self.column_heights;
+    }
+
+    pub fn state_key(&self) -> StateKey {
+        let height = self.height();
+        let profile = self.column_heights.iter().map(|h| height - h).collect();
+
+        return StateKey { shape: self.next_shape, jet: self.next_jet, profile };
+    }
+
+    /// The cells a landed rock took up in the chamber.
+    pub fn landed_cells(&self, landing: &Landing) -> Vec<(usize, usize)> {
+        return self.config.shapes[landing.shape]
+            .cells()
+            .into_iter()
+            .map(|(x, y)| (landing.x + x, landing.y + y))
+            .collect();
+    }
+
+    /// Drops the next rock and lets the jets push it around until it comes to rest.
+    pub fn drop_rock(&mut self) -> Landing {
+        let shape_index = self.next_shape;
+        let shape = &self.config.shapes[shape_index];
+        let mut x = self.config.spawn_left;
+        let mut y = self.height() + self.config.spawn_gap;
+
+        loop {
+            let jet = self.config.jets[self.next_jet];
+            self.next_jet = (self.next_jet + 1) % self.config.jets.len();
+
+            let pushed = match jet {
+                Jet::Left => x.checked_sub(1),
+                Jet::Right => Some(x + 1),
+            };
+            if let Some(pushed) = pushed.filter(|&pushed| self.fits(shape, pushed, y)) {
+                x = pushed;
+            }
+
+            if y == 0 || !self.fits(shape, x, y - 1) {
+                break;
+            }
+            y -= 1;
+        }
+
+        self.settle(shape_index, x, y);
+        self.next_shape = (self.next_shape + 1) % self.config.shapes.len();
+        self.rocks_dropped += 1;
+
+        return Landing { shape: shape_index, x, y };
+    }
+
+    /// The height of the tower once `rocks` rocks have been dropped in total, skipping ahead
+    /// as soon as the state of the chamber repeats. The chamber is left where the simulation stopped.
+    pub fn height_after(&mut self, rocks: u64) -> u64 {
+        return self.height_after_observed(rocks, |_, _| {});
+    }
+
+    /// Same as `height_after`, calling `observe` with every rock it actually simulates.
+    pub fn height_after_observed<F: FnMut(&Chamber, &Landing)>(&mut self, rocks: u64, mut observe: F) -> u64 {
+        assert!(rocks >= self.rocks_dropped, "The chamber has already dropped {} rocks", self.rocks_dropped);
+
+        let steps = rocks - self.rocks_dropped;
+        let base = self.height() as u64;
+        let mut deltas: Vec<i64> = Vec::new();
+        let mut detector = RepeatDetector::new();
+
+        let mut cycle = detector.push(self.state_key());
+        while cycle.is_none() {
+            if deltas.len() as u64 == steps {
+                return self.height() as u64;
+            }
+
+            let previous = self.height();
+            let landing = self.drop_rock();
+            observe(self, &landing);
+            deltas.push((self.height() - previous) as i64);
+
+            cycle = detector.push(self.state_key());
+        }
+
+        return base + cycle.unwrap().extrapolate(&deltas, steps) as u64;
+    }
+
+    fn fits(&self, shape: &Shape, x: usize, y: usize) -> bool {
+        if x + shape.width > self.config.width {
+            return false;
+        }
+
+        return shape.rows
+            .iter()
+            .enumerate()
+            .all(|(dy, row)| self.rows.get(y + dy).is_none_or(|settled| settled & (row << x) == 0));
+    }
+
+    fn settle(&mut self, shape_index: usize, x: usize, y: usize) {
+        let shape = &self.config.shapes[shape_index];
+        for (dy, row) in shape.rows.iter().enumerate() {
+            if *row == 0 {
+                continue;
+            }
+            if self.rows.len() <= y + dy {
+                self.rows.resize(y + dy + 1, 0);
+            }
+            self.rows[y + dy] |= row << x;
+        }
+
+        for (cx, cy) in shape.cells() {
+            let height = &mut self.column_heights[x + cx];
+            *height = (*height).max(y + cy + 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Chamber, ChamberConfig, ChamberError, Jet, Shape};
+
+    fn chamber(width: usize, shapes: &str, jets: &str) -> Chamber {
+        let config = ChamberConfig {
+            width,
+            shapes: Shape::parse_list(shapes).unwrap(),
+            jets: Jet::parse_pattern(jets).unwrap(),
+            spawn_left: 2,
+            spawn_gap: 3,
+        };
+
+        Chamber::new(config).unwrap()
+    }
+
+    #[test]
+    fn shapes_parse_from_ascii_art_bottom_row_first() {
+        let shapes = Shape::parse_list("####\n\n..#\n..#\n###\n").unwrap();
+
+        assert_eq!(shapes.len(), 2);
+        assert_eq!((shapes[0].width(), shapes[0].height()), (4, 1));
+        assert_eq!(shapes[1].cells(), vec![(0, 0), (1, 0), (2, 0), (2, 1), (2, 2)]);
+        assert_eq!(Shape::parse("#x"), Err(ChamberError::InvalidCell('x')));
+        assert_eq!(Shape::parse("..\n.."), Err(ChamberError::EmptyShape));
+        assert_eq!(Jet::parse_pattern("<>?"), Err(ChamberError::InvalidJet('?')));
+    }
+
+    #[test]
+    fn rocks_are_pushed_by_jets_and_stack_up() {
+        let mut chamber = chamber(7, "####\n\n##\n##", ">>><");
+
+        // Pushed right three times, then left once: the dash ends up against the right wall and back one.
+        let landing = chamber.drop_rock();
+        assert_eq!((landing.x, landing.y), (2, 0));
+        assert_eq!(chamber.height(), 1);
+        assert_eq!(chamber.column_heights(), &[0, 0, 1, 1, 1, 1, 0]);
+
+        let landing = chamber.drop_rock();
+        assert_eq!((landing.shape, landing.y), (1, 1));
+        assert_eq!(chamber.height(), 3);
+        assert!(chamber.is_rock(landing.x, 2));
+    }
+
+    #[test]
+    fn config_is_checked_before_simulating() {
+        let config = |width| ChamberConfig {
+            width,
+            shapes: Shape::parse_list("####").unwrap(),
+            jets: vec![Jet::Left],
+            spawn_left: 2,
+            spawn_gap: 3,
+        };
+
+        assert!(matches!(Chamber::new(config(5)), Err(ChamberError::ShapeDoesNotFit(0))));
+        assert!(matches!(Chamber::new(config(65)), Err(ChamberError::TooWide(65))));
+        assert!(Chamber::new(config(6)).is_ok());
+    }
+
+    #[test]
+    fn height_after_matches_dropping_every_rock() {
+        let tower = || chamber(7, "####\n\n.#.\n###\n.#.\n\n##\n##", "><<>>><<<>>");
+
+        let mut simulated = tower();
+        (0..5000).for_each(|_| { simulated.drop_rock(); });
+
+        assert_eq!(tower().height_after(0), 0);
+        assert_eq!(tower().height_after(5000), simulated.height() as u64);
+    }
+}
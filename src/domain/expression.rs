@@ -0,0 +1,304 @@
+//! Arithmetic expression trees over exact rationals, with constant folding and solving of
+//! equations that are linear in one unknown.
+
+use std::{collections::HashMap, fmt};
+
+use fraction::{BigFraction, Sign, Zero, One};
+use num::ToPrimitive;
+
+pub type Rational = BigFraction;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+impl Operator {
+    pub fn apply(self, left: &Rational, right: &Rational) -> Result<Rational, ExpressionError> {
+        match self {
+            Operator::Add => Ok(left + right),
+            Operator::Subtract => Ok(left - right),
+            Operator::Multiply => Ok(left * right),
+            Operator::Divide if right.is_zero() => Err(ExpressionError::DivisionByZero),
+            Operator::Divide => Ok(left / right),
+        }
+    }
+
+    pub fn symbol(self) -> char {
+        match self {
+            Operator::Add => '+',
+            Operator::Subtract => '-',
+            Operator::Multiply => '*',
+            Operator::Divide => '/',
+        }
+    }
+
+    fn precedence(self) -> u8 {
+        match self {
+            Operator::Add | Operator::Subtract => 1,
+            Operator::Multiply | Operator::Divide => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExpressionError {
+    DivisionByZero,
+    UnboundVariable(String),
+    /// The unknown is multiplied by itself or divided by, so the equation isn't linear in it.
+    NonLinear(String),
+    NoSolution,
+    InfiniteSolutions,
+}
+
+impl fmt::Display for ExpressionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExpressionError::DivisionByZero => write!(f, "division by zero"),
+            ExpressionError::UnboundVariable(name) => write!(f, "no value for {}", name),
+            ExpressionError::NonLinear(name) => write!(f, "the equation is not linear in {}", name),
+            ExpressionError::NoSolution => write!(f, "the equation has no solution"),
+            ExpressionError::InfiniteSolutions => write!(f, "every value solves the equation"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Constant(Rational),
+    Variable(String),
+    Binary(Box<Expression>, Operator, Box<Expression>),
+}
+
+impl Expression {
+    pub fn constant<T: Into<Rational>>(value: T) -> Self {
+        return Expression::Constant(value.into());
+    }
+
+    pub fn variable(name: &str) -> Self {
+        return Expression::Variable(name.to_string());
+    }
+
+    pub fn binary(left: Expression, operator: Operator, right: Expression) -> Self {
+        return Expression::Binary(Box::new(left), operator, Box::new(right));
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        match self {
+            Expression::Constant(_) => false,
+            Expression::Variable(variable) => variable == name,
+            Expression::Binary(left, _, right) => left.contains(name) || right.contains(name),
+        }
+    }
+
+    pub fn evaluate(&self, bindings: &HashMap<String, Rational>) -> Result<Rational, ExpressionError> {
+        match self {
+            Expression::Constant(value) => Ok(value.clone()),
+            Expression::Variable(name) => bindings.get(name).cloned().ok_or_else(|| ExpressionError::UnboundVariable(name.clone())),
+            Expression::Binary(left, operator, right) => operator.apply(&left.evaluate(bindings)?, &right.evaluate(bindings)?),
+        }
+    }
+
+    /// Folds every subtree without variables into a constant and drops additions of zero and
+    /// multiplications by one. Divisions by zero are left in place for `evaluate` to report.
+    pub fn fold(self) -> Expression {
+        let Expression::Binary(left, operator, right) = self else {
+            return self;
+        };
+
+        let (left, right) = (left.fold(), right.fold());
+        match (&left, operator, &right) {
+            (Expression::Constant(l), _, Expression::Constant(r)) => match operator.apply(l, r) {
+                Ok(value) => Expression::Constant(value),
+                Err(_) => Expression::binary(left, operator, right),
+            },
+            (_, Operator::Add | Operator::Subtract, Expression::Constant(r)) if r.is_zero() => left,
+            (Expression::Constant(l), Operator::Add, _) if l.is_zero() => right,
+            (_, Operator::Multiply | Operator::Divide, Expression::Constant(r)) if r.is_one() => left,
+            (Expression::Constant(l), Operator::Multiply, _) if l.is_one() => right,
+            _ => Expression::binary(left, operator, right),
+        }
+    }
+
+    // The expression as `coefficient * unknown + constant`.
+    fn linear(&self, unknown: &str) -> Result<Linear, ExpressionError> {
+        match self {
+            Expression::Constant(value) => Ok(Linear { coefficient: Rational::zero(), constant: value.clone() }),
+            Expression::Variable(name) if name == unknown => Ok(Linear { coefficient: Rational::one(), constant: Rational::zero() }),
+            Expression::Variable(name) => Err(ExpressionError::UnboundVariable(name.clone())),
+            Expression::Binary(left, operator, right) => {
+                let (l, r) = (left.linear(unknown)?, right.linear(unknown)?);
+                match operator {
+                    Operator::Add => Ok(Linear { coefficient: l.coefficient + r.coefficient, constant: l.constant + r.constant }),
+                    Operator::Subtract => Ok(Linear { coefficient: l.coefficient - r.coefficient, constant: l.constant - r.constant }),
+                    Operator::Multiply if l.coefficient.is_zero() => Ok(r.scale(&l.constant)),
+                    Operator::Multiply if r.coefficient.is_zero() => Ok(l.scale(&r.constant)),
+                    Operator::Divide if r.coefficient.is_zero() && r.constant.is_zero() => Err(ExpressionError::DivisionByZero),
+                    Operator::Divide if r.coefficient.is_zero() => Ok(l.scale(&(Rational::one() / r.constant))),
+                    _ => Err(ExpressionError::NonLinear(unknown.to_string())),
+                }
+            }
+        }
+    }
+
+    // Children that bind looser than their parent, and negative or fractional constants, get brackets.
+    fn precedence(&self) -> u8 {
+        match self {
+            Expression::Constant(value) if value.sign() == Some(Sign::Minus) || !is_integer(value) => 0,
+            Expression::Binary(_, operator, _) => operator.precedence(),
+            _ => 3,
+        }
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expression::Constant(value) => write!(f, "{}", value),
+            Expression::Variable(name) => write!(f, "{}", name),
+            Expression::Binary(left, operator, right) => {
+                let precedence = operator.precedence();
+                let left_brackets = left.precedence() < precedence;
+                let right_brackets = right.precedence() < precedence
+                    || (right.precedence() == precedence && matches!(operator, Operator::Subtract | Operator::Divide));
+
+                write_child(f, left, left_brackets)?;
+                write!(f, " {} ", operator.symbol())?;
+                write_child(f, right, right_brackets)
+            }
+        }
+    }
+}
+
+fn write_child(f: &mut fmt::Formatter, child: &Expression, brackets: bool) -> fmt::Result {
+    match brackets {
+        true => write!(f, "({})", child),
+        false => write!(f, "{}", child),
+    }
+}
+
+struct Linear {
+    coefficient: Rational,
+    constant: Rational,
+}
+
+impl Linear {
+    fn scale(self, factor: &Rational) -> Linear {
+        return Linear { coefficient: &self.coefficient * factor, constant: &self.constant * factor };
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Equation {
+    pub left: Expression,
+    pub right: Expression,
+}
+
+impl Equation {
+    pub fn new(left: Expression, right: Expression) -> Self {
+        return Equation { left, right };
+    }
+
+    pub fn fold(self) -> Equation {
+        return Equation { left: self.left.fold(), right: self.right.fold() };
+    }
+
+    /// Solves for `unknown`, which may appear any number of times on either side as long as
+    /// the equation stays linear in it.
+    pub fn solve_for(&self, unknown: &str) -> Result<Rational, ExpressionError> {
+        let left = self.left.linear(unknown)?;
+        let right = self.right.linear(unknown)?;
+        let coefficient = left.coefficient - right.coefficient;
+        let constant = right.constant - left.constant;
+
+        if coefficient.is_zero() {
+            return Err(match constant.is_zero() {
+                true => ExpressionError::InfiniteSolutions,
+                false => ExpressionError::NoSolution,
+            });
+        }
+
+        return Ok(constant / coefficient);
+    }
+}
+
+impl fmt::Display for Equation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} = {}", self.left, self.right)
+    }
+}
+
+pub fn is_integer(value: &Rational) -> bool {
+    value.denom().is_some_and(|d| d.is_one())
+}
+
+/// The value as an `i64`, if it is a whole number that fits.
+pub fn to_i64(value: &Rational) -> Option<i64> {
+    if !is_integer(value) {
+        return None;
+    }
+
+    let magnitude = value.numer()?.to_i64()?;
+    match value.sign()? {
+        Sign::Plus => Some(magnitude),
+        Sign::Minus => Some(-magnitude),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{Equation, Expression, ExpressionError, Operator, Rational, to_i64};
+
+    fn x() -> Expression {
+        Expression::variable("x")
+    }
+
+    fn number(value: i64) -> Expression {
+        Expression::constant(value)
+    }
+
+    #[test]
+    fn folding_collapses_constant_subtrees() {
+        let expression = Expression::binary(
+            Expression::binary(number(4), Operator::Multiply, number(3)),
+            Operator::Subtract,
+            Expression::binary(x(), Operator::Multiply, Expression::binary(number(2), Operator::Divide, number(2))),
+        );
+
+        let folded = expression.fold();
+        assert_eq!(folded, Expression::binary(number(12), Operator::Subtract, x()));
+        assert_eq!(folded.to_string(), "12 - x");
+
+        let bindings = HashMap::from([("x".to_string(), Rational::from(5))]);
+        assert_eq!(to_i64(&folded.evaluate(&bindings).unwrap()), Some(7));
+    }
+
+    #[test]
+    fn solves_with_the_unknown_on_both_sides_and_inexact_division() {
+        // (x + 1) / 3 = 10 - x / 2
+        let equation = Equation::new(
+            Expression::binary(Expression::binary(x(), Operator::Add, number(1)), Operator::Divide, number(3)),
+            Expression::binary(number(10), Operator::Subtract, Expression::binary(x(), Operator::Divide, number(2))),
+        );
+
+        assert_eq!(equation.solve_for("x"), Ok(Rational::new(58u8, 5u8)));
+        assert_eq!(equation.to_string(), "(x + 1) / 3 = 10 - x / 2");
+    }
+
+    #[test]
+    fn reports_equations_it_cannot_solve() {
+        let square = Equation::new(Expression::binary(x(), Operator::Multiply, x()), number(4));
+        let never = Equation::new(Expression::binary(x(), Operator::Add, number(1)), x());
+        let always = Equation::new(Expression::binary(x(), Operator::Subtract, x()), number(0));
+
+        assert_eq!(square.solve_for("x"), Err(ExpressionError::NonLinear("x".to_string())));
+        assert_eq!(never.solve_for("x"), Err(ExpressionError::NoSolution));
+        assert_eq!(always.solve_for("x"), Err(ExpressionError::InfiniteSolutions));
+        assert_eq!(number(1).fold().evaluate(&HashMap::new()), Ok(Rational::from(1)));
+    }
+}
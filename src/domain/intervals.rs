@@ -0,0 +1,192 @@
+use std::{cmp, fmt};
+
+/// A set of integers stored as sorted, disjoint, inclusive `(start, end)` ranges.
+/// Touching ranges such as `1-3` and `4-6` are merged into `1-6`.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct IntervalSet {
+    ranges: Vec<(i64, i64)>
+}
+
+impl IntervalSet {
+    pub fn new() -> Self {
+        return IntervalSet { ranges: Vec::new() };
+    }
+
+    pub fn from_range(start: i64, end: i64) -> Self {
+        let mut set = IntervalSet::new();
+        set.insert(start, end);
+        return set;
+    }
+
+    /// Inserts the inclusive range `start..=end`, merging it with any ranges it touches.
+    /// Empty ranges (`start > end`) are ignored.
+    pub fn insert(&mut self, start: i64, end: i64) {
+        if start > end {
+            return;
+        }
+
+        let first = self.ranges.partition_point(|r| r.1 < start - 1);
+        let last = self.ranges.partition_point(|r| r.0 <= end + 1);
+
+        if first == last {
+            self.ranges.insert(first, (start, end));
+            return;
+        }
+
+        let merged = (cmp::min(start, self.ranges[first].0), cmp::max(end, self.ranges[last - 1].1));
+        self.ranges.splice(first..last, [merged]);
+    }
+
+    pub fn contains(&self, value: i64) -> bool {
+        let index = self.ranges.partition_point(|r| r.1 < value);
+        return index < self.ranges.len() && self.ranges[index].0 <= value;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.ranges.is_empty();
+    }
+
+    /// Total number of integers covered.
+    pub fn len(&self) -> i64 {
+        return self.ranges.iter().map(|(s, e)| e - s + 1).sum();
+    }
+
+    pub fn ranges(&self) -> impl Iterator<Item = (i64, i64)> + '_ {
+        return self.ranges.iter().copied();
+    }
+
+    /// The uncovered ranges between consecutive covered ranges.
+    pub fn gaps(&self) -> impl Iterator<Item = (i64, i64)> + '_ {
+        return self.ranges.windows(2).map(|w| (w[0].1 + 1, w[1].0 - 1));
+    }
+
+    pub fn union(&self, other: &IntervalSet) -> Self {
+        return self.ranges().chain(other.ranges()).collect();
+    }
+
+    pub fn intersect(&self, other: &IntervalSet) -> Self {
+        let mut result = Vec::new();
+        let (mut i, mut j) = (0, 0);
+
+        while i < self.ranges.len() && j < other.ranges.len() {
+            let (a, b) = (self.ranges[i], other.ranges[j]);
+            let start = cmp::max(a.0, b.0);
+            let end = cmp::min(a.1, b.1);
+            if start <= end {
+                result.push((start, end));
+            }
+
+            if a.1 < b.1 {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+
+        return IntervalSet { ranges: result };
+    }
+
+    pub fn subtract(&self, other: &IntervalSet) -> Self {
+        let mut result = Vec::new();
+        let mut j = 0;
+
+        for &(start, end) in self.ranges.iter() {
+            let mut cursor = start;
+            while j < other.ranges.len() && other.ranges[j].1 < cursor {
+                j += 1;
+            }
+
+            let mut k = j;
+            while k < other.ranges.len() && other.ranges[k].0 <= end {
+                let (cut_start, cut_end) = other.ranges[k];
+                if cut_start > cursor {
+                    result.push((cursor, cut_start - 1));
+                }
+                cursor = cmp::max(cursor, cut_end + 1);
+                k += 1;
+            }
+
+            if cursor <= end {
+                result.push((cursor, end));
+            }
+        }
+
+        return IntervalSet { ranges: result };
+    }
+}
+
+/// Collecting sorts the ranges first, so building from `n` ranges is `O(n log n)`.
+impl FromIterator<(i64, i64)> for IntervalSet {
+    fn from_iter<I: IntoIterator<Item = (i64, i64)>>(iter: I) -> Self {
+        let mut input: Vec<(i64, i64)> = iter.into_iter().filter(|(s, e)| s <= e).collect();
+        input.sort_unstable();
+
+        let mut ranges: Vec<(i64, i64)> = Vec::with_capacity(input.len());
+        for (start, end) in input {
+            match ranges.last_mut() {
+                Some(last) if start <= last.1 + 1 => last.1 = cmp::max(last.1, end),
+                _ => ranges.push((start, end))
+            }
+        }
+
+        return IntervalSet { ranges };
+    }
+}
+
+impl fmt::Display for IntervalSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let parts: Vec<String> = self.ranges.iter().map(|(s, e)| format!("{}..={}", s, e)).collect();
+        write!(f, "{{{}}}", parts.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IntervalSet;
+
+    #[test]
+    fn interval_set_merges_overlapping_and_touching_ranges() {
+        let mut set = IntervalSet::new();
+        set.insert(10, 12);
+        set.insert(1, 3);
+        set.insert(4, 6);
+        set.insert(11, 20);
+
+        assert_eq!(set.ranges().collect::<Vec<_>>(), vec![(1, 6), (10, 20)]);
+        assert_eq!(set.len(), 17);
+        assert_eq!(set.gaps().collect::<Vec<_>>(), vec![(7, 9)]);
+    }
+
+    #[test]
+    fn interval_set_collects_like_inserting() {
+        let ranges = [(5, 9), (-2, 1), (0, 3), (12, 12), (8, 10)];
+
+        let collected: IntervalSet = ranges.iter().copied().collect();
+        let mut inserted = IntervalSet::new();
+        ranges.iter().for_each(|(s, e)| inserted.insert(*s, *e));
+
+        assert_eq!(collected, inserted);
+        assert_eq!(collected.to_string(), "{-2..=3, 5..=10, 12..=12}");
+    }
+
+    #[test]
+    fn interval_set_can_check_membership() {
+        let set: IntervalSet = vec![(1, 3), (7, 9)].into_iter().collect();
+
+        assert!(set.contains(1));
+        assert!(set.contains(9));
+        assert!(!set.contains(5));
+        assert!(!set.contains(10));
+    }
+
+    #[test]
+    fn interval_set_can_intersect_and_subtract() {
+        let a: IntervalSet = vec![(0, 10), (20, 30)].into_iter().collect();
+        let b: IntervalSet = vec![(5, 25)].into_iter().collect();
+
+        assert_eq!(a.intersect(&b).ranges().collect::<Vec<_>>(), vec![(5, 10), (20, 25)]);
+        assert_eq!(a.subtract(&b).ranges().collect::<Vec<_>>(), vec![(0, 4), (26, 30)]);
+        assert_eq!(b.subtract(&a).ranges().collect::<Vec<_>>(), vec![(11, 19)]);
+        assert!(IntervalSet::from_range(6, 8).subtract(&a).is_empty());
+    }
+}
@@ -0,0 +1,369 @@
+//! Numbers written in a balanced base, where every digit can be negative as well as positive,
+//! like the SNAFU numbers the elves use (base 5, digits `=`, `-`, `0`, `1`, `2`).
+//! Arithmetic works on the digits directly, so numbers can grow as long as they like.
+
+use std::{cmp::Ordering, fmt, hash::Hash, iter::Sum, marker::PhantomData, ops::{Add, Mul, Neg, Sub}, str::FromStr};
+
+use num::{BigInt, ToPrimitive, Zero};
+
+/// An odd radix and the characters for its digits, from the most negative to the most positive.
+pub trait BalancedBase: fmt::Debug + Clone + Copy + PartialEq + Eq + Hash {
+    const DIGITS: &'static [char];
+
+    fn radix() -> i32 {
+        Self::DIGITS.len() as i32
+    }
+
+    // The largest digit. Digits run from `-half()` to `half()`.
+    fn half() -> i32 {
+        Self::DIGITS.len() as i32 / 2
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Quinary;
+
+impl BalancedBase for Quinary {
+    const DIGITS: &'static [char] = &['=', '-', '0', '1', '2'];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Ternary;
+
+impl BalancedBase for Ternary {
+    const DIGITS: &'static [char] = &['-', '0', '+'];
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BalancedError {
+    Empty,
+    InvalidDigit(char),
+}
+
+impl fmt::Display for BalancedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BalancedError::Empty => write!(f, "a number needs at least one digit"),
+            BalancedError::InvalidDigit(c) => write!(f, "'{}' is not a digit in this base", c),
+        }
+    }
+}
+
+/// A number in the balanced base `B`. Digits are stored least significant first, without
+/// leading zeros, so zero has no digits at all.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Balanced<B: BalancedBase> {
+    digits: Vec<i8>,
+    base: PhantomData<B>,
+}
+
+pub type Snafu = Balanced<Quinary>;
+pub type BalancedTernary = Balanced<Ternary>;
+
+impl<B: BalancedBase> Balanced<B> {
+    pub fn zero() -> Self {
+        return Balanced { digits: Vec::new(), base: PhantomData };
+    }
+
+    fn from_digits(mut digits: Vec<i8>) -> Self {
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+
+        return Balanced { digits, base: PhantomData };
+    }
+
+    pub fn is_zero(&self) -> bool {
+        return self.digits.is_empty();
+    }
+
+    /// -1, 0 or 1. A balanced number has the sign of its leading digit.
+    pub fn signum(&self) -> i32 {
+        return self.digits.last().map_or(0, |&d| (d as i32).signum());
+    }
+
+    /// The value as an `i64`, if it fits.
+    pub fn to_i64(&self) -> Option<i64> {
+        return self.to_bigint().to_i64();
+    }
+
+    pub fn to_bigint(&self) -> BigInt {
+        return self.digits.iter().rev().fold(BigInt::zero(), |n, &d| n * B::radix() + d as i32);
+    }
+
+    // Splits `value` into a digit and a carry such that `value == digit + carry * radix`.
+    fn split(value: i32) -> (i8, i32) {
+        let digit = (value + B::half()).rem_euclid(B::radix()) - B::half();
+        return (digit as i8, (value - digit) / B::radix());
+    }
+
+    // Multiplies by a single digit, carrying as we go.
+    fn times_digit(&self, factor: i8) -> Self {
+        let mut digits = Vec::with_capacity(self.digits.len() + 1);
+        let mut carry = 0;
+        for &d in self.digits.iter() {
+            let (digit, next) = Self::split(d as i32 * factor as i32 + carry);
+            digits.push(digit);
+            carry = next;
+        }
+        while carry != 0 {
+            let (digit, next) = Self::split(carry);
+            digits.push(digit);
+            carry = next;
+        }
+
+        return Self::from_digits(digits);
+    }
+}
+
+impl<B: BalancedBase> Default for Balanced<B> {
+    fn default() -> Self {
+        Self::zero()
+    }
+}
+
+impl<B: BalancedBase> FromStr for Balanced<B> {
+    type Err = BalancedError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(BalancedError::Empty);
+        }
+
+        let digits = s
+            .chars()
+            .rev()
+            .map(|c| match B::DIGITS.iter().position(|&d| d == c) {
+                Some(i) => Ok((i as i32 - B::half()) as i8),
+                None => Err(BalancedError::InvalidDigit(c)),
+            })
+            .collect::<Result<Vec<i8>, BalancedError>>()?;
+
+        return Ok(Self::from_digits(digits));
+    }
+}
+
+impl<B: BalancedBase> fmt::Display for Balanced<B> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "{}", B::DIGITS[B::half() as usize]);
+        }
+
+        let text: String = self.digits.iter().rev().map(|&d| B::DIGITS[(d as i32 + B::half()) as usize]).collect();
+        write!(f, "{}", text)
+    }
+}
+
+impl<B: BalancedBase> From<i64> for Balanced<B> {
+    fn from(n: i64) -> Self {
+        Self::from(&BigInt::from(n))
+    }
+}
+
+impl<B: BalancedBase> From<&BigInt> for Balanced<B> {
+    fn from(n: &BigInt) -> Self {
+        let radix = BigInt::from(B::radix());
+        let mut rest = n.clone();
+        let mut digits = Vec::new();
+
+        while !rest.is_zero() {
+            // The remainder takes the sign of `rest`, so it is within one radix of zero either way.
+            let remainder = (&rest % &radix).to_i32().unwrap();
+            let (digit, carry) = Self::split(remainder);
+            digits.push(digit);
+            rest = &rest / &radix + carry;
+        }
+
+        return Self::from_digits(digits);
+    }
+}
+
+impl<B: BalancedBase> Add for &Balanced<B> {
+    type Output = Balanced<B>;
+
+    fn add(self, rhs: Self) -> Balanced<B> {
+        let len = self.digits.len().max(rhs.digits.len());
+        let mut digits = Vec::with_capacity(len + 1);
+        let mut carry = 0;
+
+        for i in 0..len {
+            let a = self.digits.get(i).copied().unwrap_or(0) as i32;
+            let b = rhs.digits.get(i).copied().unwrap_or(0) as i32;
+            let (digit, next) = Balanced::<B>::split(a + b + carry);
+            digits.push(digit);
+            carry = next;
+        }
+        digits.push(carry as i8);
+
+        return Balanced::from_digits(digits);
+    }
+}
+
+impl<B: BalancedBase> Add for Balanced<B> {
+    type Output = Balanced<B>;
+
+    fn add(self, rhs: Self) -> Balanced<B> {
+        &self + &rhs
+    }
+}
+
+// Negating a balanced number just flips every digit.
+impl<B: BalancedBase> Neg for &Balanced<B> {
+    type Output = Balanced<B>;
+
+    fn neg(self) -> Balanced<B> {
+        Balanced::from_digits(self.digits.iter().map(|d| -d).collect())
+    }
+}
+
+impl<B: BalancedBase> Neg for Balanced<B> {
+    type Output = Balanced<B>;
+
+    fn neg(self) -> Balanced<B> {
+        -&self
+    }
+}
+
+impl<B: BalancedBase> Sub for &Balanced<B> {
+    type Output = Balanced<B>;
+
+    fn sub(self, rhs: Self) -> Balanced<B> {
+        self + &-rhs
+    }
+}
+
+impl<B: BalancedBase> Sub for Balanced<B> {
+    type Output = Balanced<B>;
+
+    fn sub(self, rhs: Self) -> Balanced<B> {
+        &self - &rhs
+    }
+}
+
+// Long multiplication: one shifted partial product per digit of `rhs`.
+impl<B: BalancedBase> Mul for &Balanced<B> {
+    type Output = Balanced<B>;
+
+    fn mul(self, rhs: Self) -> Balanced<B> {
+        rhs.digits
+            .iter()
+            .enumerate()
+            .filter(|(_, &d)| d != 0)
+            .fold(Balanced::zero(), |product, (shift, &d)| {
+                let mut partial = self.times_digit(d);
+                if !partial.is_zero() {
+                    partial.digits.splice(0..0, std::iter::repeat_n(0, shift));
+                }
+                &product + &partial
+            })
+    }
+}
+
+impl<B: BalancedBase> Mul for Balanced<B> {
+    type Output = Balanced<B>;
+
+    fn mul(self, rhs: Self) -> Balanced<B> {
+        &self * &rhs
+    }
+}
+
+impl<B: BalancedBase> Ord for Balanced<B> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self - other).signum().cmp(&0)
+    }
+}
+
+impl<B: BalancedBase> PartialOrd for Balanced<B> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<B: BalancedBase> Sum for Balanced<B> {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::zero(), |sum, n| &sum + &n)
+    }
+}
+
+impl<'a, B: BalancedBase> Sum<&'a Balanced<B>> for Balanced<B> {
+    fn sum<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+        iter.fold(Self::zero(), |sum, n| &sum + n)
+    }
+}
+
+impl<B: BalancedBase> From<Balanced<B>> for BigInt {
+    fn from(n: Balanced<B>) -> Self {
+        n.to_bigint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num::BigInt;
+
+    use super::{BalancedTernary, Snafu};
+
+    const EXAMPLES: [(i64, &str); 10] = [
+        (1, "1"),
+        (3, "1="),
+        (8, "2="),
+        (10, "20"),
+        (20, "1-0"),
+        (2022, "1=11-2"),
+        (12345, "1-0---0"),
+        (314159265, "1121-1110-1=0"),
+        (1747, "1=-0-2"),
+        (4890, "2=-1=0"),
+    ];
+
+    #[test]
+    fn converts_both_ways() {
+        for (n, text) in EXAMPLES {
+            let snafu: Snafu = text.parse().unwrap();
+            assert_eq!(snafu.to_i64(), Some(n));
+            assert_eq!(Snafu::from(n).to_string(), text);
+        }
+
+        assert_eq!(Snafu::from(0).to_string(), "0");
+        assert_eq!("000".parse::<Snafu>().unwrap(), Snafu::zero());
+        assert!("12x".parse::<Snafu>().is_err());
+        assert!("".parse::<Snafu>().is_err());
+    }
+
+    #[test]
+    fn arithmetic_matches_integers() {
+        let values = [-4890_i64, -57, -1, 0, 1, 2, 3, 37, 2022, 314159265];
+        for a in values {
+            for b in values {
+                let (x, y) = (Snafu::from(a), Snafu::from(b));
+                assert_eq!((&x + &y).to_i64(), Some(a + b));
+                assert_eq!((&x - &y).to_i64(), Some(a - b));
+                assert_eq!((&x * &y).to_i64(), Some(a * b));
+                assert_eq!(x.cmp(&y), a.cmp(&b));
+            }
+            assert_eq!((-Snafu::from(a)).to_i64(), Some(-a));
+        }
+    }
+
+    #[test]
+    fn sums_past_the_range_of_i64() {
+        let big: Snafu = "2".repeat(40).parse().unwrap();
+        let total: Snafu = std::iter::repeat_n(big.clone(), 1000).sum();
+
+        assert_eq!(big.to_i64(), None);
+        assert_eq!(total.to_bigint(), big.to_bigint() * 1000);
+        assert_eq!(Snafu::from(&total.to_bigint()), total);
+        assert_eq!((&total * &big).to_bigint(), total.to_bigint() * big.to_bigint());
+        assert_eq!(Snafu::from(&BigInt::from(-12345)).to_i64(), Some(-12345));
+    }
+
+    #[test]
+    fn works_in_balanced_ternary() {
+        let eight: BalancedTernary = "+0-".parse().unwrap();
+
+        assert_eq!(eight.to_i64(), Some(8));
+        assert_eq!(BalancedTernary::from(-8).to_string(), "-0+");
+        assert_eq!((&eight * &BalancedTernary::from(3)).to_string(), "+0-0");
+    }
+}
@@ -1,4 +1,4 @@
-use std::{ops::{Add, Sub}, fmt, collections::HashSet };
+use std::{ops::{Add, Sub, Neg, Mul}, fmt, collections::HashSet };
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
 pub struct Vertex {
@@ -22,6 +22,10 @@ impl Vertex {
     pub fn sides_touching(&self, other: &HashSet<Vertex>) -> i32 {
         return self.neighbors().iter().filter(|p| other.contains(&p)).count() as i32;
     }
+
+    pub fn dot(&self, other: Vertex) -> i32 {
+        return self.x * other.x + self.y * other.y + self.z * other.z;
+    }
 }
 
 impl Add for Vertex {
@@ -40,6 +44,22 @@ impl Sub for Vertex {
     }
 }
 
+impl Neg for Vertex {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self {x: -self.x, y: -self.y, z: -self.z}
+    }
+}
+
+impl Mul<i32> for Vertex {
+    type Output = Self;
+
+    fn mul(self, scale: i32) -> Self {
+        Self {x: self.x * scale, y: self.y * scale, z: self.z * scale}
+    }
+}
+
 impl fmt::Display for Vertex {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "x: {}, y: {}, z: {}", self.x, self.y, self.z)
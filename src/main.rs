@@ -1,6 +1,9 @@
+// The code base spells out `return` at the end of functions, which clippy would rather it didn't.
+#![allow(clippy::needless_return)]
+
 use std::env;
 use std::{fs, fmt::Display};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::Instant;
 use crate::solutions::*;
 use clap::Parser;
@@ -8,6 +11,7 @@ use clap::Parser;
 pub mod domain;
 pub mod solutions;
 pub mod tools;
+pub mod viz;
 
 static ANSI_ITALIC: &str = "\x1b[3m";
 static ANSI_BOLD: &str = "\x1b[1m";
@@ -16,7 +20,36 @@ static ANSI_RESET: &str = "\x1b[0m";
 #[derive(Parser)]
 struct RunArgument {
     pattern: String,
-    day: i32
+    day: i32,
+    /// Print debug output such as rendered grids; repeat for step-by-step traces
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+    /// Play the simulation in the terminal after each part
+    #[arg(long)]
+    visualize: bool,
+    /// Write images and animations of the simulation into this directory
+    #[arg(long, value_name = "DIR", num_args = 0..=1, default_missing_value = "output")]
+    export: Option<PathBuf>,
+    /// Image format for exported stills and animation frames
+    #[arg(long, value_enum, default_value = "png")]
+    image_format: viz::export::ImageFormat,
+    /// Puzzle-specific setting such as `agents=3` or `minutes=40` for day 16; repeatable
+    #[arg(short = 'o', long = "option", value_name = "KEY=VALUE", value_parser = tools::options::parse_pair)]
+    options: Vec<(String, String)>,
+    /// Print day 22's edge-stitching table and check it before walking
+    #[arg(long)]
+    dump_edges: bool
+}
+
+fn play_recording(title: &str) {
+    if !viz::is_recording() {
+        return;
+    }
+
+    let frames = viz::take_frames();
+    if let Err(error) = viz::player::play(&frames, title) {
+        println!("Could not play the visualization: {}", error);
+    }
 }
 
 fn print_result<T: Display>(func: impl FnOnce(String) -> T, input: String) {
@@ -36,14 +69,27 @@ macro_rules! ship_it_extreme {
         println!("🎄 {}{}{} 🎄", ANSI_BOLD, $day_name, ANSI_RESET);
         println!("🎄 {}Part 1{} 🎄", ANSI_BOLD, ANSI_RESET);
         print_result(part_one, $input);
+        play_recording(&format!("{} part 1", $day_name));
         println!("🎄 {}Part 2{} 🎄", ANSI_BOLD, ANSI_RESET);
         print_result(part_two, $input);
+        play_recording(&format!("{} part 2", $day_name));
         println!("----");
     }};
 }
 
 fn main() {
     let run_arguement = RunArgument::parse();
+    tools::verbosity::set_level(run_arguement.verbose);
+    run_arguement.options.iter().for_each(|(key, value)| tools::options::set(key, value));
+    if run_arguement.dump_edges {
+        tools::options::set("dump-edges", "true");
+    }
+    if run_arguement.visualize {
+        viz::start_recording();
+    }
+    if let Some(directory) = run_arguement.export {
+        viz::export::enable(directory, run_arguement.image_format);
+    }
     env::set_var("RUST_BACKTRACE", "1");
     match run_arguement.day {
         2 => ship_it_extreme!(day02, load_file("day2_input.txt"), "Day 2"),
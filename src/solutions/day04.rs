@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use crate::domain::intervals::IntervalSet;
 
 pub fn part_one(input: String) -> usize {
 
@@ -24,19 +24,15 @@ pub fn part_two(input: String) -> usize {
 
 
 struct Elf {
-    assignments: HashSet<i32>,
-    low_end: i32,
-    high_end: i32
+    assignments: IntervalSet
 }
 
  impl Elf {
      pub fn new(assignment_string: String) -> Self {
         let splits:Vec<&str> = assignment_string.split("-").into_iter().collect();
-        let (low_end, high_end) = (splits[0].parse::<i32>().unwrap(), splits[1].parse::<i32>().unwrap());
+        let (low_end, high_end) = (splits[0].parse::<i64>().unwrap(), splits[1].parse::<i64>().unwrap());
         return Elf { 
-            assignments: { (low_end)..(high_end + 1) }.into_iter().collect(),
-            low_end: low_end,
-            high_end: high_end
+            assignments: IntervalSet::from_range(low_end, high_end)
         }
      }
  }
@@ -60,7 +56,7 @@ impl AssignmentPair {
         let elf_2 = &self.elves[1];
 
         fn full_contain(lhs: &Elf, rhs: &Elf) -> bool {
-            return lhs.low_end <= rhs.low_end && lhs.high_end >= rhs.high_end;
+            return rhs.assignments.subtract(&lhs.assignments).is_empty();
         }
 
         return full_contain(elf_1, elf_2) || full_contain(elf_2, elf_1);
@@ -70,10 +66,7 @@ impl AssignmentPair {
         let elf_1 = &self.elves[0];
         let elf_2 = &self.elves[1];
 
-        let intersection: HashSet<i32> = elf_1.assignments.intersection(&elf_2.assignments).copied().collect();
-        let count = intersection.len();
-
-        return count > 0;
+        return !elf_1.assignments.intersect(&elf_2.assignments).is_empty();
     }
 
 }
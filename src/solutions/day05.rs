@@ -1,6 +1,8 @@
+use crate::tools::parse;
+
 pub fn part_one(input: String) -> String { 
 
-    let splits:Vec<&str> = input.split("\n\n").into_iter().collect();
+    let splits:Vec<&str> = parse::blocks(&input);
 
     let storage = Storage::new(splits[0].to_string());
 
@@ -15,7 +17,7 @@ pub fn part_one(input: String) -> String {
 }
 
 pub fn part_two(input: String) -> String { 
-    let splits:Vec<&str> = input.split("\n\n").into_iter().collect();
+    let splits:Vec<&str> = parse::blocks(&input);
 
     let mut storage = Storage::new(splits[0].to_string());
 
@@ -49,12 +51,12 @@ struct CraneInstruction {
 
 impl CraneInstruction {
     pub fn new(instruction_string: String) -> Self {
-        let split: Vec<&str> = instruction_string.split_ascii_whitespace().into_iter().collect();
+        let (_, numbers) = parse::n_integers::<i32>(3)(&instruction_string).unwrap();
 
         return CraneInstruction { 
-            amount: split[1].parse::<i32>().unwrap(), 
-            source: split[3].parse::<i32>().unwrap(), 
-            destination: split[5].parse::<i32>().unwrap() 
+            amount: numbers[0], 
+            source: numbers[1], 
+            destination: numbers[2] 
         }
     }
 }
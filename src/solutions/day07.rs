@@ -1,250 +1,486 @@
-use std::{path::{Path, PathBuf}};
+use std::{collections::{BTreeMap, BTreeSet}, error, fmt};
 
-pub fn part_one(input: String) -> usize { 
-    let fs = FileSystem { root: Directory { name: "/".to_string(), files: Vec::new(), directories: Vec::new() }};
-    let commands = parse_commands(input); 
-    let filled_system = process_commands(commands, fs);
-    return filled_system.root.get_child_directories_at_or_below_size(100000).into_iter().map(|dir| dir.get_directory_size()).sum();
+use crate::tools::verbosity;
+
+pub fn part_one(input: String) -> usize {
+    let fs = replay(&input).unwrap_or_else(|e| panic!("Bad terminal transcript: {}", e));
+
+    if verbosity::is_debug() {
+        print!("{}", fs.tree());
+    }
+
+    return fs.find(|size| size <= 100000).into_iter().map(|dir| fs.size(dir)).sum();
 }
 
 pub fn part_two(input: String) -> usize {
     const TOTAL_SIZE:usize = 70000000;
     const UPDATE_SIZE:usize = 30000000;
 
-    let fs = FileSystem { root: Directory { name: "/".to_string(), files: Vec::new(), directories: Vec::new() }};
-    let filled_system = process_commands(parse_commands(input), fs);
-
-    let unusued_space = TOTAL_SIZE - filled_system.root.get_directory_size();
-    let space_needed_for_update = UPDATE_SIZE - unusued_space;
+    let fs = replay(&input).unwrap_or_else(|e| panic!("Bad terminal transcript: {}", e));
 
-    let mut viable_directories = filled_system.root.get_child_directories_at_or_above_size(space_needed_for_update);
-    viable_directories.sort_by(|a,b| a.get_directory_size().cmp(&b.get_directory_size()));
+    if verbosity::is_debug() {
+        fs.du().iter().for_each(|(path, size)| println!("{}\t{}", size, path));
+    }
 
-    let lowest = viable_directories.first().unwrap().get_directory_size();
+    let unusued_space = TOTAL_SIZE - fs.size(FileSystem::ROOT);
+    let space_needed_for_update = UPDATE_SIZE.saturating_sub(unusued_space);
 
-    return lowest;
+    return fs
+        .find(|size| size >= space_needed_for_update)
+        .into_iter()
+        .map(|dir| fs.size(dir))
+        .min()
+        .unwrap();
 }
 
-fn parse_commands(input: String) -> Vec<Commands> {
-    return input.lines().map(|command| {
-        let terms:Vec<&str> = command.split_whitespace().collect();
-        let split = terms.as_slice();
-        if split[0].starts_with("$") {
-            if split[1].starts_with("cd") && split[2].starts_with("..") {
-                return Commands::ChangeDirectoryUp;
-            }
-            if split[1].starts_with("cd") {
-                return Commands::ChangeDirectory(split[2].to_string());
-            }
-            if split[1].starts_with("ls") {
-                return Commands::List;
+/// Rebuilds the file system seen in a terminal session of `cd` and `ls` commands.
+/// Only directories that an `ls` has shown can be entered, and listing a directory again
+/// has to show exactly what was listed before.
+pub fn replay(transcript: &str) -> Result<FileSystem, TranscriptError> {
+    let mut fs = FileSystem::new();
+    let mut cwd = FileSystem::ROOT;
+    let mut listing: Option<Listing> = None;
+
+    for (index, line) in transcript.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        let at_line = |error: FsError| TranscriptError { line: index + 1, error };
+        let terms: Vec<&str> = line.split_whitespace().collect();
+
+        if terms.first() == Some(&"$") {
+            if let Some(finished) = listing.take() {
+                finished.check(&mut fs)?;
             }
         }
-        if split[0].starts_with("dir") {
-            return Commands::AddDirectory(split[1].to_string());
+
+        match terms.as_slice() {
+            ["$", "cd", target] => cwd = fs.change_directory(cwd, target).map_err(at_line)?,
+            ["$", "ls"] => listing = Some(Listing::start(&fs, cwd, index + 1)),
+            ["$", ..] => return Err(at_line(FsError::UnknownCommand(line.trim().to_string()))),
+            _ => {
+                let Some(current) = listing.as_mut() else {
+                    return Err(at_line(FsError::OutputWithoutCommand(line.trim().to_string())));
+                };
+                match terms.as_slice() {
+                    ["dir", name] => { fs.add_directory(cwd, name).map_err(at_line)?; }
+                    [size, name] => {
+                        let size = size.parse::<usize>().map_err(|_| at_line(FsError::Malformed(line.trim().to_string())))?;
+                        fs.add_file(cwd, name, size).map_err(at_line)?;
+                    }
+                    _ => return Err(at_line(FsError::Malformed(line.trim().to_string())))
+                }
+                current.seen.insert(terms[1].to_string());
+            }
         }
-        return Commands::AddFile(split[1].to_string(), split[0].to_string().parse::<usize>().unwrap())
-    }).collect();
+    }
+
+    if let Some(finished) = listing {
+        finished.check(&mut fs)?;
+    }
+
+    return Ok(fs);
 }
 
-fn process_commands(commands:Vec<Commands>, mut fs:FileSystem) -> FileSystem { 
-    let mut path = PathBuf::from("/");
+/// An `ls` in progress, remembering what the directory held if it had been listed before.
+struct Listing {
+    directory: NodeId,
+    line: usize,
+    previous: Option<BTreeSet<String>>,
+    seen: BTreeSet<String>
+}
+
+impl Listing {
+    fn start(fs: &FileSystem, directory: NodeId, line: usize) -> Self {
+        let previous = fs.is_listed(directory).then(|| fs.children(directory).into_iter().map(|c| fs.name(c).to_string()).collect());
+        return Listing { directory, line, previous, seen: BTreeSet::new() };
+    }
 
-    commands.into_iter().for_each(|command| {
-        match command {
-            Commands::ChangeDirectory(dir_name) => path.push(dir_name),
-            Commands::ChangeDirectoryUp =>  { path.pop(); return ();},
-            Commands::List => { return () },
-            Commands::AddFile(file_name, size) => fs.add_file_to_directory_at_path(path.as_path(), File { size: size, name: file_name }),
-            Commands::AddDirectory(dir_name) => fs.add_directory_to_directory_at_path(path.as_path(), dir_name)
+    // Entries listed before must all be listed again. New ones are already in the file system by
+    // now, so anything seen that wasn't there before shows up as added.
+    fn check(self, fs: &mut FileSystem) -> Result<(), TranscriptError> {
+        if let Some(previous) = self.previous {
+            if previous != self.seen {
+                let missing = previous.difference(&self.seen).cloned().collect();
+                let added = self.seen.difference(&previous).cloned().collect();
+                let error = FsError::ListingChanged { path: fs.path(self.directory), missing, added };
+                return Err(TranscriptError { line: self.line, error });
+            }
         }
-    });
 
-    return fs;
+        fs.mark_listed(self.directory);
+        return Ok(());
+    }
 }
 
-enum Commands {
-    ChangeDirectory(String),
-    ChangeDirectoryUp,
-    List,
-    AddFile(String, usize),
-    AddDirectory(String)
+pub type NodeId = usize;
+
+#[derive(Debug)]
+enum NodeKind {
+    Directory(BTreeMap<String, NodeId>),
+    File
+}
+
+#[derive(Debug)]
+struct Node {
+    name: String,
+    parent: Option<NodeId>,
+    kind: NodeKind,
+    // Total size of everything below, kept up to date as files are added.
+    size: usize,
+    // Whether an `ls` of this directory has been seen.
+    listed: bool
 }
 
-struct FileSystem {
-    root: Directory
+/// A directory tree stored in one arena, so nodes are addressed by `NodeId`
+/// and directory sizes are cached instead of walked on every query.
+#[derive(Debug)]
+pub struct FileSystem {
+    nodes: Vec<Node>
 }
 
-#[allow(dead_code)]
 impl FileSystem {
+    pub const ROOT: NodeId = 0;
 
-    pub fn add_directory_to_directory_at_path(&mut self, directory_path: &Path, name: String) { 
-        let mut path_stack:Vec<&str> = directory_path.components().map(|x| x.as_os_str().to_str().unwrap()).collect();
+    pub fn new() -> Self {
+        return FileSystem { nodes: vec![Node { name: "/".to_string(), parent: None, kind: NodeKind::Directory(BTreeMap::new()), size: 0, listed: false }] };
+    }
 
-        FileSystem::add_directory_to_directory_recursive(&mut self.root, &mut path_stack, name);
+    /// Adds a directory, or returns the existing one if it was already listed.
+    pub fn add_directory(&mut self, parent: NodeId, name: &str) -> Result<NodeId, FsError> {
+        match self.child(parent, name) {
+            Some(existing) if self.is_directory(existing) => Ok(existing),
+            Some(_) => Err(FsError::KindConflict(self.child_path(parent, name))),
+            None => self.insert(parent, name, NodeKind::Directory(BTreeMap::new()), 0)
+        }
     }
 
-    pub fn add_file_to_directory_at_path(&mut self, directory_path: &Path, child_file: File) { 
-        let mut path_stack:Vec<&str> = directory_path.components().map(|x| x.as_os_str().to_str().unwrap()).collect();
+    /// Adds a file and grows every directory above it. Seeing the same file again is fine,
+    /// seeing it with a different size is not.
+    pub fn add_file(&mut self, parent: NodeId, name: &str, size: usize) -> Result<NodeId, FsError> {
+        match self.child(parent, name) {
+            Some(existing) if self.is_directory(existing) => return Err(FsError::KindConflict(self.child_path(parent, name))),
+            Some(existing) if self.nodes[existing].size != size => {
+                return Err(FsError::SizeMismatch { path: self.path(existing), listed: self.nodes[existing].size, now: size });
+            }
+            Some(existing) => return Ok(existing),
+            None => ()
+        }
 
-        FileSystem::add_file_to_directory_recursive(&mut self.root, &mut path_stack, child_file);
+        let id = self.insert(parent, name, NodeKind::File, size)?;
+        let mut ancestor = Some(parent);
+        while let Some(directory) = ancestor {
+            self.nodes[directory].size += size;
+            ancestor = self.nodes[directory].parent;
+        }
+
+        return Ok(id);
     }
 
-    fn add_file_to_directory_recursive(directory: &mut Directory, path_stack: &mut Vec<&str>, child_file: File) {
-        if path_stack.len() > 1 {
-            path_stack.remove(0);
-            FileSystem::add_file_to_directory_recursive(directory.get_child_directory_mut(path_stack.first().unwrap().to_string()), path_stack, child_file);
-        }
-        else {
-            directory.add_child_file(File { name: child_file.name.to_string(), size: child_file.size });
+    fn insert(&mut self, parent: NodeId, name: &str, kind: NodeKind, size: usize) -> Result<NodeId, FsError> {
+        if name.is_empty() || name.contains('/') || name == "." || name == ".." {
+            return Err(FsError::Malformed(name.to_string()));
         }
+
+        let id = self.nodes.len();
+        match &mut self.nodes[parent].kind {
+            NodeKind::Directory(children) => children.insert(name.to_string(), id),
+            NodeKind::File => return Err(FsError::NotADirectory(self.path(parent)))
+        };
+        self.nodes.push(Node { name: name.to_string(), parent: Some(parent), kind, size, listed: false });
+
+        return Ok(id);
     }
 
-    
-    fn add_directory_to_directory_recursive(directory: &mut Directory, path_stack: &mut Vec<&str>, name: String) {
-        if path_stack.len() > 1 {
-            path_stack.remove(0);
-            FileSystem::add_directory_to_directory_recursive(directory.get_child_directory_mut(path_stack.first().unwrap().to_string()), path_stack, name);
+    /// Follows `cd` rules from `cwd`: absolute or relative paths, with `..` and `.` segments.
+    /// Every directory on the way has to exist already.
+    pub fn change_directory(&self, cwd: NodeId, target: &str) -> Result<NodeId, FsError> {
+        let mut current = if target.starts_with('/') { FileSystem::ROOT } else { cwd };
+
+        for segment in target.split('/').filter(|s| !s.is_empty() && *s != ".") {
+            current = match segment {
+                ".." => self.nodes[current].parent.ok_or(FsError::AboveRoot)?,
+                name => match self.child(current, name) {
+                    Some(child) if !self.is_directory(child) => return Err(FsError::NotADirectory(self.path(child))),
+                    Some(child) => child,
+                    None => return Err(FsError::NoSuchDirectory(self.child_path(current, name)))
+                }
+            };
         }
-        else {
-            directory.add_child_directory(Directory { files: Vec::new(), directories: Vec::new(), name: name });
+
+        return Ok(current);
+    }
+
+    pub fn is_listed(&self, directory: NodeId) -> bool {
+        return self.nodes[directory].listed;
+    }
+
+    pub fn mark_listed(&mut self, directory: NodeId) {
+        self.nodes[directory].listed = true;
+    }
+
+    pub fn child(&self, directory: NodeId, name: &str) -> Option<NodeId> {
+        match &self.nodes[directory].kind {
+            NodeKind::Directory(children) => children.get(name).copied(),
+            NodeKind::File => None
         }
     }
 
-    pub fn get_directory_at_path(&self, directory_path: &Path) -> &Directory {
-        let mut current_directory = &self.root;
+    pub fn children(&self, directory: NodeId) -> Vec<NodeId> {
+        match &self.nodes[directory].kind {
+            NodeKind::Directory(children) => children.values().copied().collect(),
+            NodeKind::File => Vec::new()
+        }
+    }
 
-        directory_path
-            .components()
-            .for_each(|f| {
-                let directory_name =  f.as_os_str().to_str().unwrap();
-                if directory_name != r#"/"# {
-                    current_directory.directories.iter().for_each(|dir| {
-                        if directory_name == dir.name {
-                            current_directory = dir;
-                        }
-                    });
-                }
+    /// Looks up an absolute path such as `/a/e`.
+    pub fn lookup(&self, path: &str) -> Option<NodeId> {
+        return path
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .try_fold(FileSystem::ROOT, |directory, name| self.child(directory, name));
+    }
 
-        });
+    pub fn name(&self, id: NodeId) -> &str {
+        return &self.nodes[id].name;
+    }
 
-        return &current_directory;
+    pub fn size(&self, id: NodeId) -> usize {
+        return self.nodes[id].size;
     }
-}
 
+    pub fn is_directory(&self, id: NodeId) -> bool {
+        return matches!(self.nodes[id].kind, NodeKind::Directory(_));
+    }
 
-struct Directory {
-    files: Vec<File>,
-    directories: Vec<Directory>,
-    name: String
-}
+    pub fn path(&self, id: NodeId) -> String {
+        return match self.nodes[id].parent {
+            None => "/".to_string(),
+            Some(parent) => self.child_path(parent, &self.nodes[id].name)
+        };
+    }
 
-#[allow(dead_code)]
-impl Directory {
-    pub fn get_directory_size(&self) -> usize {
-        let files_size = self.files.iter().map(|x| x.size).sum::<usize>();
-        let directories_size = self.directories.iter().map(|x| x.get_directory_size()).sum::<usize>();
-        return files_size + directories_size;
+    fn child_path(&self, parent: NodeId, name: &str) -> String {
+        return match parent {
+            FileSystem::ROOT => format!("/{}", name),
+            _ => format!("{}/{}", self.path(parent), name)
+        };
     }
 
-    pub fn get_child_directories_at_or_below_size(&self, size:usize) -> Vec<&Directory> {
-        let mut directories:Vec<&Directory> = self.directories.iter().filter(|f| f.get_directory_size() < size).collect();
-        let mut child_directory_recursive:Vec<&Directory> = self.directories.iter().map(|dir| dir.get_child_directories_at_or_below_size(size)).flatten().collect();
-        directories.append(&mut child_directory_recursive);
+    /// Every directory, parents before their children and siblings by name.
+    pub fn directories(&self) -> Vec<NodeId> {
+        let mut directories = Vec::new();
+        let mut stack = vec![FileSystem::ROOT];
+        while let Some(directory) = stack.pop() {
+            directories.push(directory);
+            stack.extend(self.children(directory).into_iter().filter(|c| self.is_directory(*c)).rev());
+        }
 
         return directories;
     }
 
-    pub fn get_child_directories_at_or_above_size(&self, size:usize) -> Vec<&Directory> {
-        let mut directories:Vec<&Directory> = self.directories.iter().filter(|f| f.get_directory_size() >= size).collect();
-        let mut child_directory_recursive:Vec<&Directory> = self.directories.iter().map(|dir| dir.get_child_directories_at_or_above_size(size)).flatten().collect();
-        directories.append(&mut child_directory_recursive);
+    /// Directories whose total size matches the predicate.
+    pub fn find<P: Fn(usize) -> bool>(&self, predicate: P) -> Vec<NodeId> {
+        return self.directories().into_iter().filter(|d| predicate(self.size(*d))).collect();
+    }
 
-        return directories;
+    /// The path and total size of every directory, like `du`.
+    pub fn du(&self) -> Vec<(String, usize)> {
+        return self.directories().into_iter().map(|d| (self.path(d), self.size(d))).collect();
     }
 
-    pub fn add_child_directory(&mut self, directory: Directory) {
-        self.directories.push(directory);
+    /// Draws the tree in the same layout as the puzzle description.
+    pub fn tree(&self) -> String {
+        let mut output = String::new();
+        self.write_tree(FileSystem::ROOT, 0, &mut output);
+        return output;
     }
 
-    pub fn add_child_file(&mut self, file: File) { 
-        self.files.push(file);
+    fn write_tree(&self, id: NodeId, depth: usize, output: &mut String) {
+        let indent = "  ".repeat(depth);
+        match self.nodes[id].kind {
+            NodeKind::Directory(_) => output.push_str(&format!("{}- {} (dir)\n", indent, self.name(id))),
+            NodeKind::File => output.push_str(&format!("{}- {} (file, size={})\n", indent, self.name(id), self.size(id)))
+        }
+
+        self.children(id).into_iter().for_each(|child| self.write_tree(child, depth + 1, output));
     }
+}
 
-    pub fn get_child_directory_mut(&mut self, name: String) -> &mut Directory { 
-        return self.directories.iter_mut().find(|p| p.name == name).unwrap();
+impl Default for FileSystem {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
-struct File {
-    size: usize,
-    name: String
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FsError {
+    UnknownCommand(String),
+    OutputWithoutCommand(String),
+    Malformed(String),
+    AboveRoot,
+    NotADirectory(String),
+    NoSuchDirectory(String),
+    KindConflict(String),
+    ListingChanged { path: String, missing: Vec<String>, added: Vec<String> },
+    SizeMismatch { path: String, listed: usize, now: usize }
 }
 
+impl fmt::Display for FsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FsError::UnknownCommand(line) => write!(f, "unknown command `{}`", line),
+            FsError::OutputWithoutCommand(line) => write!(f, "`{}` isn't the output of an ls", line),
+            FsError::Malformed(line) => write!(f, "can't read `{}`", line),
+            FsError::AboveRoot => write!(f, "cd .. from /"),
+            FsError::NotADirectory(path) => write!(f, "{} is a file, not a directory", path),
+            FsError::NoSuchDirectory(path) => write!(f, "cd into {}, which no ls has shown", path),
+            FsError::ListingChanged { path, missing, added } => {
+                write!(f, "{} was listed again without {:?} and with {:?}", path, missing, added)
+            }
+            FsError::KindConflict(path) => write!(f, "{} is listed as both a file and a directory", path),
+            FsError::SizeMismatch { path, listed, now } => write!(f, "{} was listed with size {} and now {}", path, listed, now)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TranscriptError {
+    pub line: usize,
+    pub error: FsError
+}
+
+impl fmt::Display for TranscriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.error)
+    }
+}
+
+impl error::Error for TranscriptError {}
+
 #[cfg(test)]
 mod tests {
-    use std::path::Path;
-
-    use crate::solutions::day07::{FileSystem, Directory};
+    use crate::solutions::day07::{FileSystem, FsError, TranscriptError, replay, part_one, part_two};
+
+    const EXAMPLE: &str = "$ cd /
+$ ls
+dir a
+14848514 b.txt
+8504156 c.dat
+dir d
+$ cd a
+$ ls
+dir e
+29116 f
+2557 g
+62596 h.lst
+$ cd e
+$ ls
+584 i
+$ cd ..
+$ cd ..
+$ cd d
+$ ls
+4060174 j
+8033020 d.log
+5626152 d.ext
+7214296 k";
 
-    use super::File;
-    
     #[test]
     fn file_system_can_have_multiple_directories_off_root() {
-        let mut file_system = FileSystem { root: Directory { files: Vec::new(), directories: Vec::new(), name: "/".to_string() } };
+        let mut file_system = FileSystem::new();
 
-        file_system.root.add_child_directory(Directory { files: Vec::new(), directories: Vec::new(), name: "abc".to_string()} );
-        file_system.root.add_child_directory(Directory { files: Vec::new(), directories: Vec::new(), name: "xkc".to_string()} );
+        file_system.add_directory(FileSystem::ROOT, "abc").unwrap();
+        file_system.add_directory(FileSystem::ROOT, "xkc").unwrap();
 
-        assert_eq!(file_system.root.directories.len(), 2);
+        assert_eq!(file_system.children(FileSystem::ROOT).len(), 2);
     }
 
     #[test]
     fn files_can_be_added_to_directory() {
-        let mut file_system = FileSystem { root: Directory { files: Vec::new(), directories: Vec::new(), name: "/".to_string() } };
-        
-        file_system.root.add_child_file(File { name: "phone.exe".to_string(), size: 299011 });
+        let mut file_system = FileSystem::new();
+
+        file_system.add_file(FileSystem::ROOT, "phone.exe", 299011).unwrap();
 
-        assert_eq!(file_system.root.files.len(), 1);
+        assert_eq!(file_system.children(FileSystem::ROOT).len(), 1);
     }
 
     #[test]
     fn files_can_be_summed_in_directory() {
-        let mut file_system = FileSystem { root: Directory { files: Vec::new(), directories: Vec::new(), name: "/".to_string() } };
+        let mut file_system = FileSystem::new();
         const FILE_SIZE:usize = 299011;
 
-        file_system.root.add_child_file(File { name: "phone.exe".to_string(), size: FILE_SIZE });
+        file_system.add_file(FileSystem::ROOT, "phone.exe", FILE_SIZE).unwrap();
 
-        assert_eq!(file_system.root.get_directory_size(), FILE_SIZE);
+        assert_eq!(file_system.size(FileSystem::ROOT), FILE_SIZE);
     }
 
     #[test]
     fn directory_can_be_found_by_path() {
-        let mut file_system = FileSystem { root: Directory { files: Vec::new(), directories: Vec::new(), name: "/".to_string() } };
+        let mut file_system = FileSystem::new();
 
-        file_system.root.add_child_directory(Directory { files: Vec::new(), directories: vec![Directory { files: Vec::new(), directories: Vec::new(), name: "8789".to_string() }], name: "abc".to_string()} );
-        file_system.root.add_child_directory(Directory { files: Vec::new(), directories: Vec::new(), name: "xkc".to_string()} );
+        let abc = file_system.add_directory(FileSystem::ROOT, "abc").unwrap();
+        let nested = file_system.add_directory(abc, "8789").unwrap();
+        file_system.add_directory(FileSystem::ROOT, "xkc").unwrap();
 
-        let dir = file_system.get_directory_at_path(Path::new("/abc/8789"));
-        assert_eq!(dir.name, "8789");
+        let dir = file_system.lookup("/abc/8789").unwrap();
+        assert_eq!(dir, nested);
+        assert_eq!(file_system.name(dir), "8789");
+        assert_eq!(file_system.path(dir), "/abc/8789");
     }
 
     #[test]
     fn file_system_can_add_file_to_directory() {
-        let mut file_system = FileSystem { root: Directory { files: Vec::new(), directories: Vec::new(), name: "/".to_string() } };
+        let mut file_system = FileSystem::new();
         const FILE_SIZE:usize = 299011;
 
-        file_system.root.add_child_directory(Directory { files: Vec::new(), directories: vec![Directory { files: Vec::new(), directories: Vec::new(), name: "8789".to_string() }], name: "abc".to_string()} );
-        file_system.root.add_child_directory(Directory { files: Vec::new(), directories:  vec![Directory { files: Vec::new(), directories: Vec::new(), name: "777".to_string() }], name: "xkc".to_string()} );
+        let abc = file_system.add_directory(FileSystem::ROOT, "abc").unwrap();
+        file_system.add_directory(abc, "8789").unwrap();
+        let xkc = file_system.add_directory(FileSystem::ROOT, "xkc").unwrap();
+        let path = file_system.add_directory(xkc, "777").unwrap();
 
-        let path = Path::new("/xkc/777");
-        file_system.add_file_to_directory_at_path(path, File { name: "christmas_spirit.exe".to_string(),size: FILE_SIZE});
+        file_system.add_file(path, "christmas_spirit.exe", FILE_SIZE).unwrap();
 
-        assert_eq!(file_system.root.get_directory_size(), FILE_SIZE);
-        
-        let directory = file_system.get_directory_at_path(path);
+        assert_eq!(file_system.size(FileSystem::ROOT), FILE_SIZE);
+        assert_eq!(file_system.size(file_system.lookup("/xkc/777").unwrap()), FILE_SIZE);
+        assert_eq!(file_system.size(abc), 0);
+    }
 
-        assert_eq!(directory.get_directory_size(), FILE_SIZE);
+    #[test]
+    fn transcript_replays_to_the_example_tree() {
+        let fs = replay(EXAMPLE).unwrap();
+
+        assert!(fs.tree().starts_with("- / (dir)\n  - a (dir)\n    - e (dir)\n      - i (file, size=584)\n    - f (file, size=29116)\n"));
+        assert_eq!(fs.du()[..3], [("/".to_string(), 48381165), ("/a".to_string(), 94853), ("/a/e".to_string(), 584)]);
+        assert_eq!(part_one(EXAMPLE.to_string()), 95437);
+        assert_eq!(part_two(EXAMPLE.to_string()), 24933642);
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn transcript_follows_relative_paths_and_rejects_inconsistencies() {
+        let relisted = format!("{}\n$ cd /a/e/../e\n$ ls\n584 i\n$ cd ../../d\n$ ls\n7214296 k\n8033020 d.log\n5626152 d.ext\n4060174 j", EXAMPLE);
+        assert_eq!(replay(&relisted).unwrap().size(FileSystem::ROOT), 48381165);
+
+        let resized = format!("{}\n$ cd /a/e\n$ ls\n585 i", EXAMPLE);
+        let above_root = "$ cd /\n$ cd ..";
+        let unlisted = "$ cd /\n123 stray.txt";
+        let unknown_directory = "$ cd /\n$ ls\ndir a\n$ cd b";
+        let forgotten = format!("{}\n$ cd /a\n$ ls\ndir e\n29116 f\n$ cd e", EXAMPLE);
+        let grown = format!("{}\n$ cd /a/e\n$ ls\n584 i\n1 new", EXAMPLE);
+
+        assert_eq!(
+            replay(&resized).unwrap_err(),
+            TranscriptError { line: 26, error: FsError::SizeMismatch { path: "/a/e/i".to_string(), listed: 584, now: 585 } }
+        );
+        assert_eq!(replay(above_root).unwrap_err(), TranscriptError { line: 2, error: FsError::AboveRoot });
+        assert_eq!(replay(unlisted).unwrap_err(), TranscriptError { line: 2, error: FsError::OutputWithoutCommand("123 stray.txt".to_string()) });
+        assert_eq!(replay(unknown_directory).unwrap_err(), TranscriptError { line: 4, error: FsError::NoSuchDirectory("/b".to_string()) });
+
+        let missing = vec!["g".to_string(), "h.lst".to_string()];
+        assert_eq!(
+            replay(&forgotten).unwrap_err(),
+            TranscriptError { line: 25, error: FsError::ListingChanged { path: "/a".to_string(), missing, added: vec![] } }
+        );
+        assert_eq!(
+            replay(&grown).unwrap_err(),
+            TranscriptError { line: 25, error: FsError::ListingChanged { path: "/a/e".to_string(), missing: vec![], added: vec!["new".to_string()] } }
+        );
+    }
+}
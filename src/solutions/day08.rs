@@ -1,5 +1,5 @@
 use std::collections::HashMap;
-use crate::domain::point::Point;
+use crate::domain::{bounds::Bounds, point::Point};
 
 const NORTH: Point = Point { x: 0, y: 1};
 const SOUTH: Point = Point { x: 0, y: -1};
@@ -47,10 +47,9 @@ impl Grid {
             });
             y_index = y_index - 1;
         });
-        let max_x = grid_map.keys().map(|pos| pos.x).max().unwrap();
-        let max_y = grid_map.keys().map(|pos| pos.y).max().unwrap();
+        let size = Bounds::from_points(grid_map.keys().copied()).unwrap().max;
 
-        return Grid { trees: grid_map, size: Point { x: max_x, y: max_y } }
+        return Grid { trees: grid_map, size: size }
     }
 
     fn check_if_tree_is_visible_in_direction(&self, position: Point, direction: Point) -> bool {
@@ -1,6 +1,6 @@
 use std::collections::HashSet;
 
-use crate::domain::point::Point;
+use crate::{domain::{bounds::Bounds, point::Point}, viz::{self, GridFrame}};
 
 const NORTH: Point = Point { x: 0, y: 1};
 const SOUTH: Point = Point { x: 0, y: -1};
@@ -39,29 +39,38 @@ fn get_unique_positions_of_rope(instructions: Vec<Instruction>, number_of_knots:
             return rope.run_instruction(instruction);
         }).flatten().collect();
 
+    emit_rope_frames(&movement_records);
+
     let unique_positions = movement_records.iter().map(|rec| rec.tail_position).collect::<HashSet<Point>>();
 
 
     return unique_positions.len();
 }
-#[allow(dead_code)]
-fn print_out_area(movement_records:Vec<MovementRecord>) { 
-    let min = movement_records.iter().map(|f| f.knots.to_vec()).flatten().min().unwrap();
-    let max = movement_records.iter().map(|f| f.knots.to_vec()).flatten().max().unwrap();
-
-    let tail_points:Vec<Point> = movement_records.iter().map(|p| p.tail_position).collect();
-
-    for x in min.x..max.x {
-        let mut row:String = "".to_string();
-        for y in min.y..max.y {
-            if tail_points.contains(&Point::new(x, y)) {
-                row = row.to_owned() + "#";
-            }
-            else {
-                row = row.to_owned() + ".";
+fn emit_rope_frames(movement_records: &Vec<MovementRecord>) {
+    if !viz::is_recording() {
+        return;
+    }
+
+    let mut visited:HashSet<Point> = HashSet::new();
+    for (step, record) in movement_records.iter().enumerate() {
+        visited.insert(record.tail_position);
+        let head = record.knots[0];
+        let view = Bounds::new(head - Point::new(30, 15), head + Point::new(30, 15));
+
+        viz::emit(|| GridFrame::from_bounds(
+            format!("Step {}, tail visited {}", step + 1, visited.len()),
+            view,
+            false,
+            Some(head),
+            |p| match record.knots.iter().position(|k| *k == p) {
+                Some(0) => 'H',
+                Some(x) if x == record.knots.len() - 1 && record.knots.len() == 2 => 'T',
+                Some(x) => char::from_digit(x as u32, 10).unwrap_or('T'),
+                None if p == Point::new(0, 0) => 's',
+                None if visited.contains(&p) => '#',
+                None => '.'
             }
-        }
-        println!("{}", row);
+        ));
     }
 }
 
@@ -133,7 +142,6 @@ impl Rope {
     }
 }
 
-#[allow(dead_code)]
 #[derive(Clone)]
 struct MovementRecord {
     knots: Vec<Point>,
@@ -1,180 +1,281 @@
-use std::collections::HashMap;
+use crate::tools::{ocr, verbosity, vm::{Control, Flow, Hook, InstructionSet, Machine, Registers}};
 
-pub fn part_one(input: String) -> i32 { 
-    let mut cpu = Cpu { current_instruction: Box::new(Noop { cycles: 0}), register: 1};
-    let mut instructions:Vec<Instructions> = input.lines().map(|line| {
-        let terms:Vec<&str> = line.split_whitespace().collect();
-        let split = terms.as_slice();
-
-        match split[0] {
-            "addx" => Instructions::Addx(split[1].parse::<i32>().unwrap()),
-            "noop" => Instructions::Noop,
-            _ => panic!("Instruction not found!")
-        }
-    }).collect();
-
-    let mut register_records:HashMap<i32,RegisterRecord> = HashMap::new();
-    for x in 1..220 + 1 {
-        if cpu.is_thread_free() && instructions.len() > 0 {
-            cpu.push_instruction(instructions.get(0).unwrap());
-            instructions.remove(0);
-        }
-        register_records.insert(x, RegisterRecord { register: cpu.register, cycle: x });
-        cpu.run_cycle();
-    }
-
-    return sum_signal_strengths(register_records, [20,60,100,140,180,220].to_vec());
-
-}
-
-fn sum_signal_strengths(register_records: HashMap<i32, RegisterRecord>, interesting_cycles: Vec<i32>) -> i32 {
+const CRT_WIDTH: i32 = 40;
+const CRT_HEIGHT: i32 = 6;
+const REGISTER: &str = "X";
 
-    let mut sum = 0;
-    interesting_cycles.into_iter().for_each(|cycle| {
-        let signal = register_records.get(&cycle).unwrap().get_signal_strength();
-        sum = sum + signal;
-    });
+pub fn part_one(input: String) -> i32 { 
+    let mut signal = SignalStrength { interesting_cycles: vec![20, 60, 100, 140, 180, 220], sum: 0 };
+    run_program(&input, &mut signal);
 
-    return sum;
+    return signal.sum;
 }
 
-pub fn part_two(input: String) -> i32 { 
-
-    let mut cpu = Cpu { current_instruction: Box::new(Noop { cycles: 0}), register: 1};
-    let mut instructions:Vec<Instructions> = input.lines().map(|line| {
-        let terms:Vec<&str> = line.split_whitespace().collect();
-        let split = terms.as_slice();
+pub fn part_two(input: String) -> String { 
+    let screen = draw_screen(&input);
 
-        match split[0] {
-            "addx" => Instructions::Addx(split[1].parse::<i32>().unwrap()),
-            "noop" => Instructions::Noop,
-            _ => panic!("Instruction not found!")
-        }
-    }).collect();
-
-    let mut register_records:HashMap<i32,RegisterRecord> = HashMap::new();
-    for cycle in 0..240 {
-        if cpu.is_thread_free() && instructions.len() > 0 {
-            cpu.push_instruction(instructions.get(0).unwrap());
-            instructions.remove(0);
-        }
-        register_records.insert(cycle, RegisterRecord { register: cpu.register, cycle });
-        if cycle % 40 == 0 {
-            println!("");
-        }
-        print_pixel(cpu.register, cycle);
-
-        cpu.run_cycle();
+    if verbosity::is_debug() {
+        println!("{}", render_screen(&screen));
     }
 
-    println!("");
-
-    return 0;
+    return ocr::read_letters(&screen);
 }
 
+fn draw_screen(input: &str) -> Vec<Vec<bool>> {
+    let mut crt = Crt { screen: vec![vec![false; CRT_WIDTH as usize]; CRT_HEIGHT as usize] };
+    run_program(input, &mut crt);
 
-fn print_pixel(sprite_position: i32, cycle: i32) {
-    let crt_position = cycle % 40;
-    let sprite_positions = [sprite_position - 1, sprite_position, sprite_position + 1].to_vec();
-    if sprite_positions.contains(&crt_position) {
-        print!("#");
-    }
-    else{
-        print!(".");
-    }
+    return crt.screen;
 }
 
-struct RegisterRecord {
-    register: i32,
-    cycle: i32
+fn handheld_cpu() -> InstructionSet {
+    InstructionSet::new()
+        .define("noop", 1, 0, |_, _| Flow::Next)
+        .define("addx", 2, 1, |registers, operands| {
+            registers.set(REGISTER, registers.get(REGISTER) + registers.value(&operands[0]));
+            Flow::Next
+        })
 }
 
-impl RegisterRecord {
-    fn get_signal_strength(&self) -> i32 {
-        return self.cycle * self.register;
-    }
-}
+fn run_program<H: Hook>(input: &str, hook: &mut H) {
+    let cpu = handheld_cpu();
+    let program = cpu.parse_program(input).unwrap_or_else(|e| panic!("Instruction not found! {}", e));
 
-
-enum Instructions {
-    Addx(i32),
-    Noop
+    let mut machine = Machine::new(&cpu, program, Registers::with(&[(REGISTER, 1)]));
+    machine.set_trace(verbosity::is_trace());
+    machine.run(hook);
 }
 
-struct Cpu { 
-    current_instruction: Box<dyn Program>,
-    register: i32
+// Adds up cycle * X during each of the interesting cycles.
+struct SignalStrength {
+    interesting_cycles: Vec<u64>,
+    sum: i32
 }
 
-impl Cpu {
-    fn run_cycle(&mut self) {
-        let (current_instruction, updated_register) = self.current_instruction.run_cycle(self.register);
-        self.current_instruction = current_instruction;
-        self.register = updated_register;
-    }
-
-    fn is_thread_free(&self) -> bool {
-        return self.current_instruction.get_remaining_cycles() <= 0;
-    }
+impl Hook for SignalStrength {
+    fn on_cycle(&mut self, cycle: u64, registers: &Registers) -> Control {
+        if self.interesting_cycles.contains(&cycle) {
+            self.sum += cycle as i32 * registers.get(REGISTER) as i32;
+        }
 
-    fn push_instruction(&mut self, instruction: &Instructions) {
-        match instruction {
-            Instructions::Addx(value) => self.current_instruction = Box::new(Addx::new(*value, 2)),
-            Instructions::Noop => self.current_instruction = Box::new(Noop::new(1))
+        match self.interesting_cycles.last() {
+            Some(last) if cycle < *last => Control::Continue,
+            _ => Control::Stop
         }
     }
 }
 
-#[derive(Debug)]
-struct Addx {
-    increment_value: i32,
-    cycles: i32
-}
-
-impl Addx {
-    fn new(increment_value: i32, cycles: i32) -> Self {
-        Addx { increment_value, cycles}
-    }
+// Draws one pixel per cycle, lit when the three pixel wide sprite centred on X covers it.
+struct Crt {
+    screen: Vec<Vec<bool>>
 }
 
+impl Hook for Crt {
+    fn on_cycle(&mut self, cycle: u64, registers: &Registers) -> Control {
+        let position = cycle as i32 - 1;
+        self.screen[(position / CRT_WIDTH) as usize][(position % CRT_WIDTH) as usize] = is_pixel_lit(registers.get(REGISTER) as i32, position);
 
-impl Program for Addx {
-    fn get_remaining_cycles(&self) -> i32 {
-        return self.cycles;
-    }
-    fn run_cycle(&self, register: i32) -> (Box<dyn Program>,i32) {
-        let cycles_left = self.cycles - 1;
-        let mut updated_register = register;
-        if cycles_left == 0 {
-            updated_register = updated_register + self.increment_value;
+        match position + 1 < CRT_WIDTH * CRT_HEIGHT {
+            true => Control::Continue,
+            false => Control::Stop
         }
-        return (Box::new(Addx { cycles: cycles_left, increment_value: self.increment_value }), updated_register);
     }
 }
 
-#[derive(Debug)]
-struct Noop {
-    cycles: i32
+fn is_pixel_lit(sprite_position: i32, cycle: i32) -> bool {
+    let crt_position = cycle % CRT_WIDTH;
+    return (sprite_position - crt_position).abs() <= 1;
 }
 
-impl Noop {
-    fn new(cycles: i32) -> Self {
-        Noop { cycles }
-    }
+fn render_screen(screen: &[Vec<bool>]) -> String {
+    return screen
+        .iter()
+        .map(|row| row.iter().map(|lit| if *lit { '#' } else { '.' }).collect::<String>())
+        .collect::<Vec<String>>()
+        .join("\n");
 }
 
-impl Program for Noop {
-    fn get_remaining_cycles(&self) -> i32 {
-        return self.cycles;
+#[cfg(test)]
+mod tests {
+    use crate::tools::ocr;
+
+    use super::{draw_screen, part_one, render_screen};
+
+    const EXAMPLE: &str = "addx 15
+addx -11
+addx 6
+addx -3
+addx 5
+addx -1
+addx -8
+addx 13
+addx 4
+noop
+addx -1
+addx 5
+addx -1
+addx 5
+addx -1
+addx 5
+addx -1
+addx 5
+addx -1
+addx -35
+addx 1
+addx 24
+addx -19
+addx 1
+addx 16
+addx -11
+noop
+noop
+addx 21
+addx -15
+noop
+noop
+addx -3
+addx 9
+addx 1
+addx -3
+addx 8
+addx 1
+addx 5
+noop
+noop
+noop
+noop
+noop
+addx -36
+noop
+addx 1
+addx 7
+noop
+noop
+noop
+addx 2
+addx 6
+noop
+noop
+noop
+noop
+noop
+addx 1
+noop
+noop
+addx 7
+addx 1
+noop
+addx -13
+addx 13
+addx 7
+noop
+addx 1
+addx -33
+noop
+noop
+noop
+addx 2
+noop
+noop
+noop
+addx 8
+noop
+addx -1
+addx 2
+addx 1
+noop
+addx 17
+addx -9
+addx 1
+addx 1
+addx -3
+addx 11
+noop
+noop
+addx 1
+noop
+addx 1
+noop
+noop
+addx -13
+addx -19
+addx 1
+addx 3
+addx 26
+addx -30
+addx 12
+addx -1
+addx 3
+addx 1
+noop
+noop
+noop
+addx -9
+addx 18
+addx 1
+addx 2
+noop
+noop
+addx 9
+noop
+noop
+noop
+addx -1
+addx 2
+addx -37
+addx 1
+addx 3
+noop
+addx 15
+addx -21
+addx 22
+addx -6
+addx 1
+noop
+addx 2
+addx 1
+noop
+addx -10
+noop
+noop
+addx 20
+addx 1
+addx 2
+addx 2
+addx -6
+addx -11
+noop
+noop
+noop";
+
+    #[test]
+    fn signal_strength_matches_example() {
+        assert_eq!(part_one(EXAMPLE.to_string()), 13140);
     }
 
-    fn run_cycle(&self, register: i32) -> (Box<dyn Program>,i32) {
-        return (Box::new(Noop { cycles: self.cycles - 1 }), register);
-    }
-}
+    #[test]
+    fn crt_draws_the_example_screen() {
+        let expected = "##..##..##..##..##..##..##..##..##..##..
+###...###...###...###...###...###...###.
+####....####....####....####....####....
+#####.....#####.....#####.....#####.....
+######......######......######......####
+#######.......#######.......#######.....";
 
+        assert_eq!(render_screen(&draw_screen(EXAMPLE)), expected);
+    }
 
-trait Program: std::fmt::Debug { 
-    fn get_remaining_cycles(&self) -> i32;
-    fn run_cycle(&self, register: i32) -> (Box<dyn Program>,i32);
+    #[test]
+    fn screen_reads_as_letters() {
+        let render = "####.###....##.###..###..#..#..##..#..#.
+#....#..#....#.#..#.#..#.#.#..#..#.#..#.
+###..#..#....#.###..#..#.##...#..#.####.
+#....###.....#.#..#.###..#.#..####.#..#.
+#....#....#..#.#..#.#.#..#.#..#..#.#..#.
+####.#.....##..###..#..#.#..#.#..#.#..#.";
+        let screen: Vec<Vec<bool>> = render.lines().map(|row| row.chars().map(|c| c == '#').collect()).collect();
+
+        assert_eq!(ocr::read_letters(&screen), "EPJBRKAH");
+        assert_eq!(ocr::read_letters(&draw_screen(EXAMPLE)), "????????");
+    }
 }
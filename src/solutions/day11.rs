@@ -1,52 +1,76 @@
-use std::{error};
-type Result<T> = std::result::Result<T, Box<dyn error::Error>>;
+use std::{error, str::FromStr};
 
+use nom::{branch::alt, combinator::map, sequence::{pair, preceded, tuple}, IResult};
 
-pub fn part_one(input: String) -> u64 {
-
-    let mut monkeys:Vec<Monkey> = Vec::new();
-    input.split("\n\n").for_each(|monkey_def| {
-        monkeys.push(Monkey::new(monkey_def).unwrap());
-    });
+use crate::tools::parse;
 
-    let mut keep_away = KeepAway { monkeys: monkeys };
-    let divisor_product = keep_away.monkeys.iter().map(|m| m.evaluate_number).product::<u64>();
-    for _ in 0..20 {
-        keep_away.run_round(divisor_product);
-    }
+pub type Result<T> = std::result::Result<T, Box<dyn error::Error>>;
 
 
-    let mut sorted_monkeys = keep_away.monkeys.to_vec();
-    sorted_monkeys.sort_by(|a,b| b.items_inspected.cmp(&a.items_inspected));
-    return sorted_monkeys[0].items_inspected * sorted_monkeys[1].items_inspected;
+pub fn part_one(input: String) -> u64 {
+    return monkey_business(&input, KeepAwayRules { rounds: 20, relief: 3, reduction: WorryReduction::None }).unwrap();
 }
 
 pub fn part_two(input: String) -> u64 {
-    let mut monkeys:Vec<Monkey> = Vec::new();
-    input.split("\n\n").for_each(|monkey_def| {
-        monkeys.push(Monkey::new(monkey_def).unwrap());
-    });
+    return monkey_business(&input, KeepAwayRules { rounds: 10000, relief: 1, reduction: WorryReduction::Modular }).unwrap();
+}
 
-    let mut keep_away = KeepAway { monkeys: monkeys };
-    let divisor_product = keep_away.monkeys.iter().map(|m| m.evaluate_number).product::<u64>();
+/// How worry levels are kept in check between inspections.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorryReduction {
+    /// Worry levels are tracked exactly. Overflowing a `u64` is reported as an error.
+    None,
+    /// Worry levels are kept modulo the lcm of every monkey's divisor, which doesn't change
+    /// any test result. Only valid without relief, since dividing doesn't survive the modulo.
+    Modular
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeepAwayRules {
+    pub rounds: usize,
+    /// Worry levels are divided by this after every inspection.
+    pub relief: u64,
+    pub reduction: WorryReduction
+}
+
+/// Plays keep away with the monkeys in `input` and multiplies the two highest inspection counts.
+pub fn monkey_business(input: &str, rules: KeepAwayRules) -> Result<u64> {
+    if rules.relief == 0 {
+        return Err("Relief has to divide worry by at least 1".into());
+    }
+    if rules.relief != 1 && rules.reduction == WorryReduction::Modular {
+        return Err("Modular reduction only works when relief is 1".into());
+    }
 
-    for _ in 0..10000 {
-        keep_away.run_round(divisor_product);
+    let monkeys = parse::blocks(input).into_iter().map(Monkey::new).collect::<Result<Vec<Monkey>>>()?;
+    let mut keep_away = KeepAway::new(monkeys, rules);
+    for _ in 0..rules.rounds {
+        keep_away.run_round()?;
     }
 
-    let mut sorted_monkeys = keep_away.monkeys.to_vec();
-    sorted_monkeys.sort_by(|a,b| b.items_inspected.cmp(&a.items_inspected));
-    return sorted_monkeys[0].items_inspected * sorted_monkeys[1].items_inspected;
+    let mut inspected: Vec<u64> = keep_away.monkeys.iter().map(|m| m.items_inspected).collect();
+    inspected.sort_by(|a, b| b.cmp(a));
+    return Ok(inspected.iter().take(2).product());
 }
 
 
 struct KeepAway {
-    monkeys: Vec<Monkey>
+    monkeys: Vec<Monkey>,
+    relief: u64,
+    modulus: Option<u64>
 }
 
 impl KeepAway {
+    fn new(monkeys: Vec<Monkey>, rules: KeepAwayRules) -> Self {
+        let modulus = match rules.reduction {
+            WorryReduction::None => None,
+            WorryReduction::Modular => Some(monkeys.iter().map(|m| m.test.divisor).fold(1, num::integer::lcm))
+        };
 
-    fn run_round(&mut self, divisor_product: u64) {
+        return KeepAway { monkeys, relief: rules.relief, modulus };
+    }
+
+    fn run_round(&mut self) -> Result<()> {
         let num_monkeys = self.monkeys.len();
 
         for i in 0..num_monkeys {
@@ -58,27 +82,31 @@ impl KeepAway {
             }
 
             for mut item in monkey_copy.items.iter().copied() {
-                item = Item { worry_level: item.worry_level % divisor_product };
-                item = monkey_copy.inspect(item);
+                item = monkey_copy.inspect(item, self.modulus)?;
+                item = Item { worry_level: item.worry_level / self.relief };
 
                 let toss_to = monkey_copy.decide(item);
 
-                self.monkeys.get_mut(toss_to as usize).unwrap().catch_mut(item);
+                self.monkeys
+                    .get_mut(toss_to as usize)
+                    .ok_or_else(|| format!("Monkey {} threw to missing monkey {}", monkey_copy.id, toss_to))?
+                    .catch_mut(item);
             }
             self.monkeys[i].items.clear();
         }
+
+        return Ok(());
     }
 
 
 }
 
-#[allow(dead_code)]
 #[derive(Debug, Clone)]
 struct Monkey {
     items: Vec<Item>,
     id: i32,
-    operation: meval::Expr,
-    evaluate_number: u64,
+    operation: MathOperation,
+    test: DivisibleTest,
     evaluate_true: i32,
     evaluate_false: i32,
     items_inspected: u64,
@@ -88,48 +116,56 @@ impl Monkey {
     fn new(monkey_def: &str) -> Result<Monkey> {
 
         fn id_parser(line: &str) -> Result<MonkeyId> {
-
-            let line = line.replace(":", "");
-            return Ok(MonkeyId {value: i32::from_str_radix(line.split_whitespace().nth(1).unwrap(), 10)? });
+            let (_, id) = preceded(parse::token("Monkey"), parse::signed::<i32>)(line).map_err(|e| e.to_owned())?;
+            return Ok(MonkeyId { value: id });
         }
 
         fn items_parser(line: &str) -> Result<Vec<Item>> {
-            return Ok(line.split(":").nth(1).unwrap().split(",").map(|item| {
-                return Item { worry_level: u64::from_str_radix(item.trim(), 10).unwrap() };
-            }).collect());
+            let (_, items) = preceded(parse::token("Starting items:"), parse::comma_separated(parse::unsigned::<u64>))(line)
+                .map_err(|e| e.to_owned())?;
+            return Ok(items.into_iter().map(|worry_level| Item { worry_level }).collect());
         }
 
-        fn operation_parser(line: &str) -> Result<meval::Expr> {
-            return Ok(line.split("=").nth(1).unwrap().to_string().parse().unwrap());
+        fn operation_parser(line: &str) -> Result<MathOperation> {
+            return preceded(parse::token("Operation:"), MathOperation::parse)(line)
+                .map(|(_, operation)| operation)
+                .map_err(|e| e.to_owned().into());
         }
 
-        fn get_last_number_in_line(line: &str) -> Result<i32> {
-            return Ok(i32::from_str_radix(line.split_whitespace().last().unwrap(), 10).unwrap());
+        fn get_last_number_in_line<T: FromStr>(line: &str) -> Result<T> {
+            let (_, numbers) = parse::all_integers::<T>(line).map_err(|e| e.to_owned())?;
+            return numbers.into_iter().last().ok_or_else(|| format!("No number in line: {}", line).into());
         }
-
-        fn get_last_number_in_line_64(line: &str) -> Result<u64> {
-            return Ok(u64::from_str_radix(line.split_whitespace().last().unwrap(), 10).unwrap());
+        let mut lines = monkey_def.lines();
+        let mut next_line = || lines.next().ok_or_else(|| format!("Monkey definition is cut short:\n{}", monkey_def));
+
+        let id = id_parser(next_line()?);
+        let items = items_parser(next_line()?);
+        let operation = operation_parser(next_line()?);
+        let divisor = get_last_number_in_line::<u64>(next_line()?)?;
+        let eval_true = MonkeyId { value: get_last_number_in_line::<i32>(next_line()?)? };
+        let eval_false = MonkeyId { value: get_last_number_in_line::<i32>(next_line()?)? };
+
+        if divisor == 0 {
+            return Err(format!("Monkey can't test divisibility by 0:\n{}", monkey_def).into());
         }
-        let mut lines = monkey_def.lines().into_iter();
-
-        let id = id_parser(lines.nth(0).unwrap());
-        let items = items_parser(lines.nth(0).unwrap());
-        let operation = operation_parser(lines.nth(0).unwrap());
-        let eval_number = get_last_number_in_line_64(lines.nth(0).unwrap()).unwrap();
-        let eval_true = MonkeyId { value: get_last_number_in_line(lines.nth(0).unwrap()).unwrap() };
-        let eval_false = MonkeyId { value: get_last_number_in_line(lines.nth(0).unwrap()).unwrap() };
 
-        return Ok(Monkey { id: id?.value, items: items?, operation: operation?, evaluate_number: eval_number, evaluate_true:eval_true.value, evaluate_false: eval_false.value, items_inspected: 0 });
+        return Ok(Monkey { id: id?.value, items: items?, operation: operation?, test: DivisibleTest { divisor }, evaluate_true:eval_true.value, evaluate_false: eval_false.value, items_inspected: 0 });
     }
 
-    fn inspect(&self, item: Item) -> Item {
-        let op = self.operation.to_owned();
-        let func = op.bind("old").unwrap();
-        return Item { worry_level: func(item.worry_level as f64).round() as u64 };
+    // With a modulus the new worry level comes back already reduced by it.
+    fn inspect(&self, item: Item, modulus: Option<u64>) -> Result<Item> {
+        let worry_level = match modulus {
+            Some(modulus) => self.operation.apply_modulo(item.worry_level, modulus),
+            None => self.operation.apply(item.worry_level)
+        };
+        let worry_level = worry_level
+            .ok_or_else(|| format!("Monkey {} pushed a worry level of {} out of range", self.id, item.worry_level))?;
+        return Ok(Item { worry_level });
     }
 
     fn decide(&self, item: Item) -> i32 {
-        if item.worry_level % self.evaluate_number as u64 == 0 {
+        if self.test.evaluate(item.worry_level) {
             return self.evaluate_true;
         }
         else {
@@ -153,35 +189,177 @@ struct Item {
     worry_level: u64
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operand {
+    Old,
+    Number(u64)
+}
+
+impl Operand {
+    fn parse(input: &str) -> IResult<&str, Self> {
+        alt((
+            map(parse::token("old"), |_| Operand::Old),
+            map(parse::ws(parse::unsigned::<u64>), Operand::Number)
+        ))(input)
+    }
+
+    fn value(&self, old: u64) -> u64 {
+        match self {
+            Operand::Old => old,
+            Operand::Number(number) => *number
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide
+}
+
+/// The `new = <operand> <operator> <operand>` line, applied with checked `u64` arithmetic or modulo a divisor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct MathOperation {
-    operation: meval::Expr
+    left: Operand,
+    operator: Operator,
+    right: Operand
+}
+
+impl MathOperation {
+    fn parse(input: &str) -> IResult<&str, Self> {
+        let operator = alt((
+            map(parse::token("+"), |_| Operator::Add),
+            map(parse::token("-"), |_| Operator::Subtract),
+            map(parse::token("*"), |_| Operator::Multiply),
+            map(parse::token("/"), |_| Operator::Divide)
+        ));
+        let (input, (left, operator, right)) = preceded(
+            pair(parse::token("new"), parse::token("=")),
+            tuple((Operand::parse, operator, Operand::parse))
+        )(input)?;
+
+        Ok((input, MathOperation { left, operator, right }))
+    }
 }
 
 impl Operation for MathOperation {
-    fn get_worry_level(&self, old: i32) -> i32 {
-        let op = self.operation.to_owned();
-        let func = op.bind("old").unwrap();
-        return func(old as f64).round() as i32;
+    fn apply(&self, old: u64) -> Option<u64> {
+        let (left, right) = (self.left.value(old), self.right.value(old));
+        match self.operator {
+            Operator::Add => left.checked_add(right),
+            Operator::Subtract => left.checked_sub(right),
+            Operator::Multiply => left.checked_mul(right),
+            Operator::Divide => left.checked_div(right)
+        }
+    }
+
+    // Worked out in `u128`, where the product of two numbers below the modulus always fits, and
+    // reduced before going back to `u64`. Subtraction wraps around the modulus, and division
+    // can't be done modulo anything, so it has no answer here.
+    fn apply_modulo(&self, old: u64, modulus: u64) -> Option<u64> {
+        let modulus = modulus as u128;
+        let (left, right) = (self.left.value(old) as u128 % modulus, self.right.value(old) as u128 % modulus);
+        let value = match self.operator {
+            Operator::Add => (left + right) % modulus,
+            Operator::Subtract => (left + modulus - right) % modulus,
+            Operator::Multiply => left * right % modulus,
+            Operator::Divide => return None
+        };
+
+        Some(value as u64)
     }
 }
 
 trait Operation: std::fmt::Debug {
-    fn get_worry_level(&self, old: i32) -> i32;
+    /// The new worry level, or `None` if it can't be represented exactly.
+    fn apply(&self, old: u64) -> Option<u64>;
+
+    /// The new worry level modulo `modulus`, or `None` if the operation doesn't survive the modulo.
+    fn apply_modulo(&self, old: u64, modulus: u64) -> Option<u64>;
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct DivisibleTest {
-    division_number: i32
+    divisor: u64
 }
 
 impl Test for DivisibleTest {
-    fn evaluate(&self, input: i32) -> bool {
-        return input % self.division_number == 0
+    fn evaluate(&self, worry_level: u64) -> bool {
+        return worry_level.is_multiple_of(self.divisor)
     }
     
 }
 
 trait Test: std::fmt::Debug {
-    fn evaluate(&self, input: i32) -> bool;
-}
\ No newline at end of file
+    fn evaluate(&self, worry_level: u64) -> bool;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{monkey_business, KeepAwayRules, MathOperation, Operand, Operation, Operator, WorryReduction};
+
+    const EXAMPLE: &str = "Monkey 0:
+  Starting items: 79, 98
+  Operation: new = old * 19
+  Test: divisible by 23
+    If true: throw to monkey 2
+    If false: throw to monkey 3
+
+Monkey 1:
+  Starting items: 54, 65, 75, 74
+  Operation: new = old + 6
+  Test: divisible by 19
+    If true: throw to monkey 2
+    If false: throw to monkey 0
+
+Monkey 2:
+  Starting items: 79, 60, 97
+  Operation: new = old * old
+  Test: divisible by 13
+    If true: throw to monkey 1
+    If false: throw to monkey 3
+
+Monkey 3:
+  Starting items: 74
+  Operation: new = old + 3
+  Test: divisible by 17
+    If true: throw to monkey 0
+    If false: throw to monkey 1";
+
+    #[test]
+    fn operations_parse_into_exact_arithmetic() {
+        let (_, square) = MathOperation::parse("new = old * old").unwrap();
+        let (_, add) = MathOperation::parse(" new = old + 6").unwrap();
+
+        assert_eq!(square, MathOperation { left: Operand::Old, operator: Operator::Multiply, right: Operand::Old });
+        assert_eq!(square.apply(3_000_000_007), Some(9_000_000_042_000_000_049));
+        assert_eq!(square.apply(u64::MAX / 2), None);
+        assert_eq!(add.apply(10), Some(16));
+    }
+
+    #[test]
+    fn modular_operations_work_past_u64_products() {
+        let (_, square) = MathOperation::parse("new = old * old").unwrap();
+        let (_, subtract) = MathOperation::parse("new = old - 7").unwrap();
+        let (_, divide) = MathOperation::parse("new = old / 2").unwrap();
+        let modulus = 10_000_000_019;
+
+        assert_eq!(square.apply(modulus - 1), None);
+        assert_eq!(square.apply_modulo(modulus - 1, modulus), Some(1));
+        assert_eq!(square.apply_modulo(3_000_000_007, modulus), Some(9_000_000_042_000_000_049 % modulus));
+        assert_eq!(subtract.apply_modulo(3, modulus), Some(modulus - 4));
+        assert_eq!(divide.apply_modulo(10, modulus), None);
+    }
+
+    #[test]
+    fn keep_away_matches_example_for_both_rule_sets() {
+        let with_relief = KeepAwayRules { rounds: 20, relief: 3, reduction: WorryReduction::None };
+        let modular = KeepAwayRules { rounds: 10000, relief: 1, reduction: WorryReduction::Modular };
+
+        assert_eq!(monkey_business(EXAMPLE, with_relief).unwrap(), 10605);
+        assert_eq!(monkey_business(EXAMPLE, modular).unwrap(), 2713310158);
+        assert!(monkey_business(EXAMPLE, KeepAwayRules { relief: 3, ..modular }).is_err());
+    }
+}
@@ -2,7 +2,7 @@ use std::{collections::{HashMap}, cell::Cell};
 
 use priority_queue::PriorityQueue;
 
-use crate::domain::point::{NORTH, Point, EAST, WEST, SOUTH};
+use crate::domain::{bounds::Bounds, point::{NORTH, Point, EAST, WEST, SOUTH}};
 
 pub fn part_one(input: String) -> i32 {
     let grid = &mut Grid::new(input.as_str());
@@ -149,9 +149,8 @@ impl Grid {
             });
             y_index = y_index - 1;
         });
-        let max_x = grid_map.keys().map(|pos| pos.x).max().unwrap();
-        let max_y = grid_map.keys().map(|pos| pos.y).max().unwrap();
-        return Grid { height_map: grid_map, size: Point { x: max_x, y: max_y }, start: start.unwrap(), end: end.unwrap() }
+        let size = Bounds::from_points(grid_map.keys().copied()).unwrap().max;
+        return Grid { height_map: grid_map, size: size, start: start.unwrap(), end: end.unwrap() }
     }
 
 
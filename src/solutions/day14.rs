@@ -1,301 +1,224 @@
-use std::{fmt::Display, collections::{HashSet, HashMap}};
+use std::fmt::Display;
 
 use itertools::Itertools;
 
-use crate::domain::point::Point;
+use crate::{domain::{bounds::Bounds, point::Point}, tools::verbosity, viz::{self, GridFrame, export::{self, Animation, Image, Palette, Rgb}}};
 
+const SOURCE: Point = Point { x: 500, y: 0 };
 
-
-pub fn part_one(_: String) -> impl Display {
-
-    0
+pub fn part_one(input: String) -> impl Display {
+    pour_sand(&input, SandMode::Abyss)
 }
 
 pub fn part_two(input: String) -> impl Display {
-    let mut grid_objects = input
-        .lines()
-        .map(|l| ParsedGridObject::new(l))
-        .collect_vec();
-
-    grid_objects.push(ParsedGridObject {
-        positions: vec![Point { x: 500, y: 0 }],
-        grid_object: GridObject::Air,
-    });
-
-    let mut grid = Grid::new(grid_objects, Point { x: 500, y: 0 }, true);
-
-    let map = grid.print_grid();
-    print!("{}", map);
-
-    let mut count = 0;
-    let mut cursor:Option<Point> = Option::None;
-    cursor = grid.tick(cursor);
-    while count < 10000000 && cursor.is_some() {
-        count = count + 1;
-        cursor = grid.tick(cursor);
-    }
+    pour_sand(&input, SandMode::Floor)
+}
 
-    println!("");
-    println!("{}", grid.print_grid());
-    println!("");
+/// What is below the lowest rock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SandMode {
+    /// Nothing, so sand that falls past the lowest rock is gone for good.
+    Abyss,
+    /// An endless floor two below the lowest rock. Sand piles up until it blocks the source.
+    Floor,
+}
 
-    let sand_count = grid
-        .grid_points
-        .values()
-        .filter(|f| matches!(f.occupied, GridObject::Sand))
-        .collect_vec()
-        .len();
-    println!("");
-    println!("Iterations {}", count);
-    println!("Units of Sand {}", sand_count);
+// Pours sand until it starts falling into the abyss or the source is blocked, and counts the grains at rest.
+fn pour_sand(input: &str, mode: SandMode) -> usize {
+    let mut cave = Cave::new(input.lines().flat_map(rock_path), SOURCE, mode);
 
-    0
-}
+    if verbosity::is_debug() {
+        println!("{}", cave.print_grid());
+    }
 
+    // With a floor the final pile can be worked out directly, unless we want to watch it grow.
+    let grains = match mode {
+        SandMode::Floor if !viz::is_recording() && !export::is_enabled() => cave.fill_reachable(),
+        _ => cave.pour(),
+    };
 
+    if verbosity::is_debug() {
+        println!("{}", cave.print_grid());
+    }
 
-struct ParsedGridObject {
-    positions: Vec<Point>,
-    grid_object: GridObject,
+    return grains;
 }
 
-impl ParsedGridObject {
-    fn new(line: &str) -> Self {
-        let points: HashSet<Point> = line
-            .split("->")
-            .into_iter()
-            .map(|coord| Point::parse(coord.to_string()))
-            .collect_vec()
-            .windows(2)
-            .map(|p| p[0].all_points_between(p[1]))
-            .flatten()
-            .collect();
-
-        ParsedGridObject {
-            positions: points.into_iter().collect_vec(),
-            grid_object: GridObject::Rock,
-        }
-    }
+fn rock_path(line: &str) -> Vec<Point> {
+    return line
+        .split("->")
+        .map(|coord| Point::parse(coord.to_string()))
+        .collect_vec()
+        .windows(2)
+        .flat_map(|p| p[0].all_points_between(p[1]))
+        .collect();
 }
 
-struct Grid {
-    grid_points: HashMap<Point, GridPoint>,
-    sand_origination: Point,
-    min: Point,
-    max: Point,
+/// The slice of the cave sand can reach, stored densely row by row with `y` pointing down.
+struct Cave {
+    cells: Vec<GridObject>,
+    bounds: Bounds,
+    source: Point,
+    lowest_rock: i32,
+    mode: SandMode,
 }
 
-impl Grid {
-    fn new(grid_objects: Vec<ParsedGridObject>, sand_origination: Point, add_floor: bool) -> Self {
-        let mut grid_points = HashMap::new();
-        let min_x = grid_objects
-            .iter()
-            .map(|p| &p.positions)
-            .flatten()
-            .map(|p| p.x)
-            .min()
-            .unwrap()
-            - 150;
-
-        let max_x = grid_objects
-            .iter()
-            .map(|p| &p.positions)
-            .flatten()
-            .map(|p| p.x)
-            .max()
-            .unwrap()
-            + 150;
-
-        let min_y = grid_objects
-            .iter()
-            .map(|p| &p.positions)
-            .flatten()
-            .map(|p| p.y)
-            .min()
-            .unwrap();
-
-        let max_y = grid_objects
-            .iter()
-            .map(|p| &p.positions)
-            .flatten()
-            .map(|p| p.y)
-            .max()
-            .unwrap()
-            + 2;
-
-        for x in min_x..max_x + 1 {
-            for y in min_y..max_y + 1 {
-                grid_points.insert(
-                    Point::new(x, y),
-                    GridPoint {
-                        occupied: GridObject::Air,
-                        at_rest: true,
-                    },
-                );
-            }
+impl Cave {
+    fn new<I: IntoIterator<Item = Point>>(rocks: I, source: Point, mode: SandMode) -> Self {
+        let rocks = rocks.into_iter().collect_vec();
+        let lowest_rock = rocks.iter().map(|p| p.y).max().unwrap_or(source.y);
+        let floor = lowest_rock + 2;
+
+        // Sand spreads at most one column per row, so it can't get further than `floor` columns from the source.
+        let pile = Bounds::new(Point::new(source.x - floor - 1, source.y), Point::new(source.x + floor + 1, floor));
+        let bounds = Bounds::from_points(rocks.iter().copied()).map_or(pile, |b| b.union(&pile));
+
+        let mut cave = Cave {
+            cells: vec![GridObject::Air; bounds.area() as usize],
+            bounds,
+            source,
+            lowest_rock,
+            mode,
+        };
+        rocks.into_iter().for_each(|p| cave.set(p, GridObject::Rock));
+        if mode == SandMode::Floor {
+            (bounds.min.x..=bounds.max.x).for_each(|x| cave.set(Point::new(x, floor), GridObject::Rock));
         }
 
-        grid_objects.iter().for_each(|object| {
-            object.positions.iter().for_each(|pos| {
-                grid_points
-                    .get_mut(pos)
-                    .unwrap()
-                    .update_point(object.grid_object, true);
-            });
-        });
-
-        if add_floor {
-            for x in min_x..max_x + 1 {
-                grid_points
-                    .get_mut(&Point { x: x, y: max_y })
-                    .unwrap()
-                    .update_point(GridObject::Rock, true);
-            }
-        }
+        return cave;
+    }
 
+    fn index(&self, p: Point) -> usize {
+        return ((p.y - self.bounds.min.y) * self.bounds.width() + (p.x - self.bounds.min.x)) as usize;
+    }
 
-        Grid {
-            grid_points: grid_points,
-            min: Point::new(min_x, min_y),
-            max: Point { x: max_x, y: max_y },
-            sand_origination: sand_origination,
+    fn get(&self, p: Point) -> GridObject {
+        if !self.bounds.contains(p) {
+            return GridObject::Air;
         }
+
+        return self.cells[self.index(p)];
     }
 
-    #[allow(unused_assignments)]
-    fn tick(&mut self, sand: Option<Point>) -> Option<Point> {
-        let mut sand_moved = false;
-        let mut sand_point: Option<Point> = Option::None;
-        if sand.is_none() {
-            let point = &mut self
-                .grid_points
-                .iter()
-                .find(|(_, gp)| {
-                    matches!(gp.occupied, GridObject::Sand) && gp.at_rest == false
-                })
-                .map(|p| p.0.clone());
-            sand_point = point.clone();
-        }
-        else {
-            sand_point = sand;
-        }
+    fn set(&mut self, p: Point, grid_object: GridObject) {
+        let index = self.index(p);
+        self.cells[index] = grid_object;
+    }
+
+    // Drops grains one at a time. The path the last grain took is kept on a stack, so the next grain
+    // starts from the last spot on it that is still open rather than from the source.
+    fn pour(&mut self) -> usize {
+        let mut path = vec![self.source];
+        let mut grains = 0;
+        let mut animation = Animation::new(40);
+
+        while let Some(&sand) = path.last() {
+            if self.mode == SandMode::Abyss && sand.y > self.lowest_rock {
+                break;
+            }
+
+            let next = [0, -1, 1]
+                .into_iter()
+                .map(|dx| sand + Point::new(dx, 1))
+                .find(|p| self.get(*p) == GridObject::Air);
 
-        if sand_point.is_some() {
-            let p = sand_point.unwrap();
-            let possible_paths = vec![
-                p + Point::new(0, 1),
-                p + Point::new(-1, 1),
-                p + Point::new(1, 1),
-            ];
-            let point = self.get_path_for_sand(possible_paths.to_vec());
-            if point.is_some() {
-                let next_position = point.unwrap();
-
-                if self.grid_points.contains_key(&next_position) {
-                    self.grid_points
-                        .entry(next_position)
-                        .and_modify(|gp| gp.update_point(GridObject::Sand, false));
-                } else {
-                    if !self.point_in_bounds(next_position) {
-                        self.add_column(next_position.x);
-                        self.grid_points
-                            .entry(next_position)
-                            .and_modify(|gp| gp.update_point(GridObject::Sand, false));
+            match next {
+                Some(next) => path.push(next),
+                None => {
+                    self.set(sand, GridObject::Sand);
+                    path.pop();
+                    grains += 1;
+
+                    if grains % 10 == 0 {
+                        viz::emit(|| self.frame(format!("{} grains at rest", grains), sand));
+                    }
+                    if export::is_enabled() && grains % 250 == 0 {
+                        animation.push(self.image());
                     }
                 }
-
-                self.grid_points
-                    .entry(p)
-                    .and_modify(|gp| gp.update_point(GridObject::Air, true));
-                
-                sand_moved = true;
-                return Option::Some(next_position);
-            } else {
-                self.grid_points
-                    .entry(p)
-                    .and_modify(|gp| gp.update_point(GridObject::Sand, true));
             }
         }
-        if !sand_moved
-            && matches!(
-                self.grid_points
-                    .get(&self.sand_origination)
-                    .unwrap()
-                    .occupied,
-                GridObject::Sand
-            )
-        {
-            return Option::None;
-        } 
-        if !sand_moved {
-            let sand_spawn = self.sand_origination;
-            self.grid_points.entry(sand_spawn).and_modify(|gp| gp.update_point(GridObject::Sand, false));
-            return Option::Some(sand_spawn);
+
+        if export::is_enabled() {
+            let name = match self.mode {
+                SandMode::Abyss => "day14_abyss",
+                SandMode::Floor => "day14_sand",
+            };
+            animation.push(self.image());
+            export::save_image(name, &self.image());
+            export::save_animation(name, &animation);
         }
 
-        return Option::None;
+        return grains;
     }
 
-    fn add_column(&mut self, x: i32) {
-        println!("Adding a column {}", x);
-        for y in self.min.y..=self.max.y {
-            self.grid_points.insert(
-                Point::new(x, y),
-                GridPoint {
-                    occupied: GridObject::Air,
-                    at_rest: true,
-                },
-            );
-        }
+    // With a floor, sand ends up in every cell that can be reached from the source by falling
+    // straight or diagonally down without going through rock, so the pile can be filled a row at a time.
+    fn fill_reachable(&mut self) -> usize {
+        let floor = self.lowest_rock + 2;
+        let width = self.bounds.width() as usize;
+        let min_x = self.bounds.min.x;
+        let column = |x: i32| (x - min_x) as usize;
+
+        let mut reachable = vec![false; width];
+        reachable[column(self.source.x)] = true;
+        let mut grains = 0;
+
+        for y in self.source.y..floor {
+            if y > self.source.y {
+                let above = reachable.clone();
+                for x in self.bounds.min.x..=self.bounds.max.x {
+                    let i = column(x);
+                    let from_above = above[i] || (i > 0 && above[i - 1]) || (i + 1 < width && above[i + 1]);
+                    reachable[i] = from_above && self.get(Point::new(x, y)) != GridObject::Rock;
+                }
+            }
 
-        self.grid_points
-            .entry(Point::new(x, self.max.y)).and_modify(|gp| gp.update_point(GridObject::Rock, true));
-        let min_x = self.grid_points.iter().map(|p| &p.0.x).max().unwrap();
+            for x in self.bounds.min.x..=self.bounds.max.x {
+                if reachable[column(x)] {
+                    self.set(Point::new(x, y), GridObject::Sand);
+                    grains += 1;
+                }
+            }
+        }
 
-        let max_x = self.grid_points.iter().map(|p| &p.0.x).min().unwrap();
-        self.max = Point::new(*max_x, self.max.y);
-        self.min = Point::new(*min_x, self.min.y);
+        return grains;
     }
-    fn point_in_bounds(&self, point: Point) -> bool {
-        if point.x < self.min.x
-            || point.x > self.max.x
-            || point.y < self.min.y
-            || point.y > self.max.y
-        {
-            return false;
-        }
 
-        true
+    fn frame(&self, caption: String, focus: Point) -> GridFrame {
+        let window = Bounds::new(focus - Point::new(60, 30), focus + Point::new(60, 30));
+        let view = window.intersection(&self.bounds).unwrap_or(self.bounds);
+
+        GridFrame::from_bounds(caption, view, true, Some(focus), |p| {
+            if p == self.source {
+                return '+';
+            }
+            match self.get(p) {
+                GridObject::Sand => 'o',
+                GridObject::Rock => '#',
+                GridObject::Air => '.',
+            }
+        })
     }
 
-    fn get_path_for_sand(&self, possible_paths: Vec<Point>) -> Option<Point> {
-        let mut path: Option<Point> = Option::None;
-        for x in possible_paths {
-            let point = match self.grid_points.get(&x) {
-                Some(grid_point) => match grid_point.occupied {
-                    GridObject::Air => Option::Some(x),
-                    _ => Option::None,
-                },
-                None => Option::Some(x),
-            };
-            if point.is_some() {
-                path = Option::Some(point.unwrap());
-                break;
+    fn image(&self) -> Image {
+        Image::from_bounds(self.bounds, true, 3, |p| {
+            if p == self.source {
+                return SAND_SOURCE;
             }
-        }
-        return path;
+            self.get(p).color()
+        })
     }
 
     fn print_grid(&self) -> String {
         let mut map = String::new();
-        for y in self.min.y..=self.max.y {
-            map.push_str("\n");
-            for x in self.min.x..=self.max.x {
-                let gp = self.grid_points.get(&Point::new(x, y)).unwrap();
-                match gp.occupied {
-                        GridObject::Sand => map.push_str("o"),
-                        GridObject::Rock => map.push_str("#"),
-                        GridObject::Air => map.push_str("."),
+        for y in self.bounds.min.y..=self.bounds.max.y {
+            map.push('\n');
+            for x in self.bounds.min.x..=self.bounds.max.x {
+                match self.get(Point::new(x, y)) {
+                    GridObject::Sand => map.push('o'),
+                    GridObject::Rock => map.push('#'),
+                    GridObject::Air => map.push('.'),
                 }
             }
         }
@@ -311,14 +234,37 @@ enum GridObject {
     Air,
 }
 
-struct GridPoint {
-    occupied: GridObject,
-    at_rest: bool,
+const SAND_SOURCE: Rgb = [255, 80, 40];
+
+impl Palette for GridObject {
+    fn color(&self) -> Rgb {
+        match self {
+            GridObject::Sand => [230, 190, 110],
+            GridObject::Rock => [110, 105, 100],
+            GridObject::Air => [30, 36, 52],
+        }
+    }
 }
 
-impl GridPoint {
-    fn update_point(&mut self, grid_object: GridObject, at_rest: bool) {
-        self.occupied = grid_object;
-        self.at_rest = at_rest;
+#[cfg(test)]
+mod tests {
+    use super::{pour_sand, rock_path, Cave, SandMode, SOURCE};
+
+    const EXAMPLE: &str = "498,4 -> 498,6 -> 496,6
+503,4 -> 502,4 -> 502,9 -> 494,9";
+
+    #[test]
+    fn sand_falls_into_the_abyss_or_piles_on_the_floor() {
+        assert_eq!(pour_sand(EXAMPLE, SandMode::Abyss), 24);
+        assert_eq!(pour_sand(EXAMPLE, SandMode::Floor), 93);
+    }
+
+    #[test]
+    fn reachability_fills_the_same_pile_as_pouring() {
+        let mut poured = Cave::new(EXAMPLE.lines().flat_map(rock_path), SOURCE, SandMode::Floor);
+        let mut filled = Cave::new(EXAMPLE.lines().flat_map(rock_path), SOURCE, SandMode::Floor);
+
+        assert_eq!(poured.pour(), filled.fill_reachable());
+        assert_eq!(poured.print_grid(), filled.print_grid());
     }
 }
@@ -1,17 +1,14 @@
 use core::panic;
-use std::cmp;
 use num::{bigint, BigInt};
 
 use itertools::Itertools;
 use nom::{
     bytes::complete::tag,
-    character::complete::{digit1},
-    combinator::{map, map_res, recognize, opt},
-    sequence::{separated_pair, tuple, preceded},
+    combinator::map,
+    sequence::{separated_pair, tuple},
     IResult,
 };
-use std::str::FromStr;
-use crate::domain::point::Point;
+use crate::{domain::{intervals::IntervalSet, point::Point}, tools::parse};
 
 pub fn part_one(input: String) -> i32 {
     let sensors = get_sensors(input);
@@ -33,12 +30,14 @@ fn calculate_frequency(point: Point) -> BigInt {
 }
 
 fn find_the_beacon(sensors: Vec<Sensor>, max_coord: i32) -> Point {
+    let search_area = IntervalSet::from_range(0, max_coord as i64);
     for row in 0..=max_coord {
-        let occupied_spaces = get_occupied_spaces_in_row_with_range(sensors.to_vec(), row, 0, max_coord);
+        let uncovered = search_area.subtract(&get_covered_ranges_in_row(&sensors, row));
 
-        if occupied_spaces != max_coord {
-            let x_location = find_x_location_of_beacon(sensors.to_vec(), row, 0, max_coord);
-            return Point::new(x_location, row);
+        let gap = uncovered.ranges().next();
+
+        if let Some((x_location, _)) = gap {
+            return Point::new(x_location as i32, row);
         }
     }
     panic!("Didn't find it");
@@ -51,89 +50,27 @@ fn get_sensors(input: String) -> Vec<Sensor> {
     .collect_vec()
 }
 
-fn get_occupied_spaces_in_row_with_range(sensors: Vec<Sensor>, row: i32, min: i32, max: i32) -> i32 {
-    let unique_values = flatten_ranges(&sensors
+fn get_covered_ranges_in_row(sensors: &[Sensor], row: i32) -> IntervalSet {
+    sensors
         .iter()
-        .map(|sensor| sensor.get_covered_range_in_row_with_range(row, min, max))
-        .filter(|pair| pair.is_some())
-        .map(|pair| pair.unwrap())
-        .collect::<std::collections::BinaryHeap<_>>().into_sorted_vec());
-
-        unique_values.iter().map(|(s, e)| 
-        {
-            e - s}
-        ).sum()
-}
-
-fn find_x_location_of_beacon(sensors: Vec<Sensor>, row: i32, min: i32, max: i32) -> i32 { 
-    let unique_values = flatten_ranges(&sensors
-        .iter()
-        .map(|sensor| sensor.get_covered_range_in_row_with_range(row, min, max))
-        .filter(|pair| pair.is_some())
-        .map(|pair| pair.unwrap())
-        .collect::<std::collections::BinaryHeap<_>>().into_sorted_vec());
-
-
-    if unique_values.len() == 2 { 
-        let first = unique_values.get(0).unwrap();
-        return first.1 + 1;
-    }
-
-    if unique_values.len() == 1 {
-        let line = unique_values.get(0).unwrap();
-
-        return match line.0.cmp(&min) {
-            cmp::Ordering::Greater => min,
-            _ => max
-        }
-    }
-
-    panic!("Help");
+        .filter_map(|sensor| sensor.get_covered_range_in_row(row))
+        .map(|(s, e)| (s as i64, e as i64))
+        .collect()
 }
 
 fn get_occupied_spaces_in_row(sensors: Vec<Sensor>, row: i32) -> i32 {
+    let covered = get_covered_ranges_in_row(&sensors, row);
 
-    let unique_values = flatten_ranges(&sensors
+    let beacons_in_row = sensors
         .iter()
-        .map(|sensor| sensor.get_covered_range_in_row(row))
-        .filter(|pair| pair.is_some())
-        .map(|pair| pair.unwrap())
-        .collect::<std::collections::BinaryHeap<_>>().into_sorted_vec());
-
+        .map(|sensor| sensor.closest_beacon)
+        .filter(|beacon| beacon.y == row && covered.contains(beacon.x as i64))
+        .unique()
+        .count();
 
-    unique_values.iter().map(|(s, e)| 
-    {
-        e - s}
-    ).sum()
+    (covered.len() - beacons_in_row as i64) as i32
 }
 
-// https://www.geeksforgeeks.org/merging-intervals/
-fn flatten_ranges(ranges: &[(i32, i32)]) -> Vec<(i32, i32)> {
-    if ranges.len() == 0 {
-        return Vec::new()
-    }
-    (!ranges.is_empty()).then_some(ranges.iter()
-        .skip(1)
-        .fold(vec![ranges[0]], |flat, &r|
-            std::iter::once(flat.len() - 1).fold(flat, |mut flat, last| {
-                match flat[last].0 <= r.0 && r.0 <= flat[last].1 {
-                    true => flat[last].1 = flat[last].1.max(r.1),
-                    _ => flat.push(r),
-                }
-
-                flat
-            })))
-        .unwrap_or_default()
-}
-
-
-fn parse_numbers(input: &str) -> IResult<&str, i32> {
-    let (i, number) = map_res(recognize(preceded(opt(tag("-")), digit1)), |s| {
-        i32::from_str(s)
-    })(input)?;
-
-    Ok((i, number))
-}
 
 fn manhattan_distance(p1:Point, p2:Point) -> i32 {
     (p1.x - p2.x).abs() + (p1.y - p2.y).abs()
@@ -156,7 +93,7 @@ impl Sensor {
 
     fn parse_sensor(input: &str) -> IResult<&str, Point> {
         let (input,_) = tag("x=")(input)?;
-        let (input, (num1,_, num2)) = tuple((parse_numbers, tag(", y="), parse_numbers))(input)?;
+        let (input, (num1,_, num2)) = tuple((parse::signed::<i32>, tag(", y="), parse::signed::<i32>))(input)?;
 
         Ok((input, Point::new(num1, num2)))
     }
@@ -164,23 +101,12 @@ impl Sensor {
     fn get_covered_range_in_row(&self, y_row: i32) -> Option<(i32, i32)> {
 
         let x_radia = self.distance - (self.position.y - y_row).abs();
-        if x_radia <= 0 {
+        if x_radia < 0 {
             return Option::None
         }
 
         return Option::Some((self.position.x - x_radia, self.position.x + x_radia));
     }
-
-    
-    fn get_covered_range_in_row_with_range(&self, y_row: i32, min: i32, max: i32) -> Option<(i32, i32)> {
-
-        let x_radia = self.distance - (self.position.y - y_row).abs();
-        if x_radia <= 0 {
-            return Option::None
-        }
-
-        return Option::Some((cmp::max(self.position.x - x_radia, min), cmp::min(self.position.x + x_radia, max)));
-    }
 }
 
 #[cfg(test)]
@@ -228,7 +154,7 @@ mod tests {
         let sensors = get_sensors(input.to_string());
         let result = get_occupied_spaces_in_row(sensors, 7);
         
-        assert_eq!(result, 18);
+        assert_eq!(result, 19);
     }
 
     #[test]
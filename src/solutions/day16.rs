@@ -1,117 +1,148 @@
-use std::cmp::Ordering;
-use std::collections::{BinaryHeap, HashSet};
-use std::{fmt::Display, collections::HashMap};
-use std::str::FromStr;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Display;
 use itertools::Itertools;
-use nom::{bytes::complete::{tag, take}, IResult, combinator::{recognize, map_res, opt}, sequence::{preceded}, character::complete::{digit1}};
-type DistanceMatrix<'a> = HashMap<String, HashMap<String, i32>>;
+use nom::{bytes::complete::{tag, take}, IResult, branch::alt, character::complete::alpha1};
+use crate::tools::{memo::Memo, options, parse};
 
+const START_VALVE: &str = "AA";
+const TEACHING_MINUTES: i32 = 4;
+// Every set of valves gets a slot in `best_by_opened`, so this many is already 64MB of it.
+const MAX_VALVES_WITH_FLOW: usize = 24;
+
+// Options: `minutes` is the time before the volcano erupts (default 30) and `agents` is how
+// many of us open valves in part two, after spending 4 of those minutes teaching the elephants.
 pub fn part_one(input: String) -> impl Display {
-    let valves = get_valves(input);
-    let distance_matrix = get_valve_distances(&valves);
-    let to_open = valves.iter().filter(|v| v.flow_rate > 0).map(|v| v.id.clone()).collect_vec();
-    recursive_path_finding(&valves, &to_open, &distance_matrix, "AA", 30, &vec!["AA".to_string()], &Vec::new()).flow
+    let network = ValveNetwork::new(&get_valves(input), START_VALVE).unwrap_or_else(|e| panic!("{}", e));
+    network.max_pressure(options::get_or("minutes", 30), 1)
 } 
 
 pub fn part_two(input: String) -> impl Display {
-    let valves = get_valves(input);
-    let distance_matrix = get_valve_distances(&valves);
-    let to_open = valves.iter().filter(|v| v.flow_rate > 0).map(|v| v.id.clone()).collect_vec();
-    let PathFit { path: no_overlap, flow } = recursive_path_finding(&valves, &to_open, &distance_matrix, "AA", 26, &vec!["AA".to_string()], &Vec::new());
-    
-    let PathFit { path: _, flow: elephant_helping } = recursive_path_finding(&valves, &to_open, &distance_matrix, "AA", 26, &vec!["AA".to_string()], &no_overlap);
-    flow + elephant_helping
+    let network = ValveNetwork::new(&get_valves(input), START_VALVE).unwrap_or_else(|e| panic!("{}", e));
+    let minutes = options::get_or("minutes", 30) - TEACHING_MINUTES;
+    network.max_pressure(minutes, options::get_or("agents", 2))
 } 
 
+/// The valves worth opening, numbered so a set of them fits in a bitmask, with the
+/// shortest walk between any two of them and from the start.
+struct ValveNetwork {
+    flow_rates: Vec<i32>,
+    // Indexed by valve number, with the start valve last. `None` where there is no way through.
+    distances: Vec<Vec<Option<i32>>>
+}
+
+impl ValveNetwork {
+    fn new(valves: &[Valve], start: &str) -> Result<Self, String> {
+        let index: HashMap<&str, usize> = valves.iter().enumerate().map(|(i, v)| (v.id.as_str(), i)).collect();
+        let tunnels = valves.iter().map(|v| v.leads_to.iter().map(|to| index[to.as_str()]).collect_vec()).collect_vec();
 
-fn recursive_path_finding<'a>(
-    valves: &Vec<Valve>,
-    to_open: &Vec<String>,
-    distance_matrix: &'a DistanceMatrix,
-    start_valve: &str,
-    minutes_left: i32,
-    path: &Vec<String>,
-    overlap: &Vec<String>
-) -> PathFit {
-    let mut paths: Vec<PathFit> = Vec::new();
-
-    for valve in to_open {
-        if overlap.contains(valve) {
-            continue;
+        let mut stops = valves.iter().enumerate().filter(|(_, v)| v.flow_rate > 0).map(|(i, _)| i).collect_vec();
+        if stops.len() > MAX_VALVES_WITH_FLOW {
+            return Err(format!("{} valves have flow, but at most {} can be tracked", stops.len(), MAX_VALVES_WITH_FLOW));
         }
-        let distance = distance_matrix[valve][start_valve];
+        stops.push(index[start]);
+
+        let distances = stops
+            .iter()
+            .map(|from| {
+                let walks = walking_distances(&tunnels, *from);
+                stops.iter().map(|to| walks[*to]).collect_vec()
+            })
+            .collect_vec();
+        let flow_rates = stops[..stops.len() - 1].iter().map(|i| valves[*i].flow_rate).collect_vec();
+
+        return Ok(ValveNetwork { flow_rates, distances });
+    }
 
-        let valve_ref = valves.iter().find(|v| v.id == *valve).unwrap();
+    fn start(&self) -> usize {
+        return self.flow_rates.len();
+    }
 
-        if distance >= minutes_left {
+    /// The most pressure released by each set of opened valves, when one agent opens exactly
+    /// that set within the time limit. Sets nobody can open in time stay at 0.
+    fn best_by_opened(&self, minutes: i32) -> Vec<i32> {
+        let mut best = vec![0; 1 << self.flow_rates.len()];
+        self.visit(self.start(), minutes, 0, 0, &mut best);
+        return best;
+    }
 
-            // Not a candidate
-            continue;
-        }
+    fn visit(&self, at: usize, minutes_left: i32, opened: usize, released: i32, best: &mut [i32]) {
+        best[opened] = best[opened].max(released);
 
-        let minutes_after_operation = minutes_left - distance - 1;
-        let flow_acheived = valve_ref.flow_rate * minutes_after_operation;
+        for (valve, flow_rate) in self.flow_rates.iter().enumerate() {
+            let Some(distance) = self.distances[at][valve] else {
+                continue;
+            };
+            let minutes_after_opening = minutes_left - distance - 1;
+            if opened & (1 << valve) != 0 || minutes_after_opening <= 0 {
+                continue;
+            }
 
-        let next_to_open:&Vec<String> = &to_open.iter().filter(|v| *v != valve).map(|v| v.clone()).collect_vec();
-        let mut branch = path.clone();
-        branch.push(valve.clone());
-        let full_path = recursive_path_finding(valves, next_to_open, distance_matrix, valve, minutes_after_operation, &branch, &overlap);
+            self.visit(valve, minutes_after_opening, opened | (1 << valve), released + flow_rate * minutes_after_opening, best);
+        }
+    }
 
-        let mut add_path = path.clone();
-        add_path.extend(full_path.path);
+    /// Splits the valves between `agents` who work at the same time, each taking a disjoint set.
+    fn max_pressure(&self, minutes: i32, agents: usize) -> i32 {
+        if agents == 0 || minutes <= 0 {
+            return 0;
+        }
+
+        // After this, `best[set]` is the most one agent can release using only valves in `set`.
+        let mut best = self.best_by_opened(minutes);
+        for valve in 0..self.flow_rates.len() {
+            for set in 0..best.len() {
+                if set & (1 << valve) != 0 {
+                    best[set] = best[set].max(best[set ^ (1 << valve)]);
+                }
+            }
+        }
 
-        paths.push(PathFit { path: add_path, flow: full_path.flow + flow_acheived });
+        let mut memo = Memo::new();
+        return team_pressure(&best, agents, best.len() - 1, &mut memo);
     }
+}
 
-    let mut best_path = PathFit { path: Vec::new(), flow: 0 };
-    for path_fit in paths {
-        if path_fit.flow > best_path.flow {
-            best_path = path_fit;
-        }
+// The most `agents` can release between them from valves in `set`, one taking `mine` and the
+// rest sharing what's left. Only the sets the split actually reaches get worked out.
+fn team_pressure(best: &[i32], agents: usize, set: usize, memo: &mut Memo<(usize, usize), i32>) -> i32 {
+    if agents == 1 {
+        return best[set];
+    }
+    if let Some(pressure) = memo.get(&(agents, set)) {
+        return pressure;
     }
-    best_path
+
+    let pressure = submasks(set).map(|mine| best[mine] + team_pressure(best, agents - 1, set ^ mine, memo)).max().unwrap();
+    memo.insert((agents, set), pressure)
 }
 
+// Every subset of `set`, including `set` itself and the empty set.
+fn submasks(set: usize) -> impl Iterator<Item = usize> {
+    let mut next = Some(set);
+    std::iter::from_fn(move || {
+        let current = next?;
+        next = if current == 0 { None } else { Some((current - 1) & set) };
+        Some(current)
+    })
+}
 
-fn get_valve_distances(valves: &Vec<Valve>) -> DistanceMatrix {
-    let mut distances = HashMap::new();
-
-    for start in valves {
-        let start = start.clone();
-        let distances_from = distances.entry(start.id.clone())
-            .or_insert(HashMap::new());
-            let mut to_visit = BinaryHeap::new();
-            let mut visited = HashSet::new();
-    
-            to_visit.push(Visit {  id: start.id, distance: 0 });
-    
-            while let Some(visit) = to_visit.pop() {
-                if !visited.insert(visit.id.clone()) {
-                    continue;
-                }
-    
-                let neighbours = &valves.iter().find(|v| v.id == visit.id.clone()).unwrap().leads_to;
-                    for neighbour in neighbours {
-                        let neighbour_clone = valves.iter().find(|v| v.id == *neighbour).unwrap().clone();
-                        let new_dist = visit.distance + 1;
-                        let use_dist = distances_from.get(&neighbour_clone.id)
-                            .map_or(true, |&current_dist| {
-                                current_dist > new_dist
-                            });
-                        
-                        if use_dist {
-                            distances_from.insert(neighbour_clone.id.clone(), new_dist);
-                            to_visit.push(Visit { id: neighbour_clone.id, distance: new_dist });
-                        }
-                    }
-                
+fn walking_distances(tunnels: &[Vec<usize>], from: usize) -> Vec<Option<i32>> {
+    let mut distances = vec![None; tunnels.len()];
+    let mut to_visit = VecDeque::from([(from, 0)]);
+    distances[from] = Some(0);
+
+    while let Some((valve, distance)) = to_visit.pop_front() {
+        for next in tunnels[valve].iter() {
+            if distances[*next].is_none() {
+                distances[*next] = Some(distance + 1);
+                to_visit.push_back((*next, distance + 1));
             }
         }
-        distances
-       
-
     }
 
+    return distances;
+}
+
 
 fn get_valves(input: String) -> Vec<Valve> {
     input
@@ -120,21 +151,6 @@ fn get_valves(input: String) -> Vec<Valve> {
     .collect_vec()
 }
 
-fn parse_numbers(input: &str) -> IResult<&str, i32> {
-    let (i, number) = map_res(recognize(preceded(opt(tag("-")), digit1)), |s| {
-        i32::from_str(s)
-    })(input)?;
-
-    Ok((i, number))
-}
-
-#[derive(Debug, Clone)]
-struct PathFit {
-    path: Vec<String>,
-    flow: i32,
-}
-
-
 #[derive(Debug, Eq, PartialEq, Hash, Clone)]
 struct Valve {
     id: String,
@@ -143,66 +159,90 @@ struct Valve {
 }
 
 
-#[derive(Debug)]
-struct Visit {
-    id: String,
-    distance: i32
-}
-
-impl<'a> Ord for Visit {
-    fn cmp(&self, other: &Self) -> Ordering {
-        other.distance.cmp(&self.distance)
-    }
-}
-
-impl<'a> PartialOrd for Visit {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
-impl<'a> PartialEq for Visit {
-    fn eq(&self, other: &Self) -> bool {
-        self.distance.eq(&other.distance)
-    }
-}
-impl<'a> Eq for Visit {}
-
 impl Valve {
     fn parse(input: &str) -> IResult<&str, Self> {
         let (input, _) = tag("Valve ")(input)?;
 
-        let (input, id) = take(2 as usize)(input)?;
+        let (input, id) = take(2usize)(input)?;
         let (input, _) = tag(" has flow rate=")(input)?;
-        let (input, flow_rate) = (parse_numbers)(input)?;
+        let (input, flow_rate) = (parse::signed::<i32>)(input)?;
         let (input, _) = (Valve::parse_out_plurals)(input)?;
 
         let (input, leads_to) = (Valve::parse_leads_to_valves)(input)?;
-        Ok((input, Valve { id: id.to_string(), flow_rate: flow_rate, leads_to: leads_to.into_iter().map(|v| v.to_string() ).collect() }))
+        Ok((input, Valve { id: id.to_string(), flow_rate, leads_to: leads_to.into_iter().map(|v| v.to_string() ).collect() }))
     }
 
     fn parse_out_plurals(input: &str) -> IResult<&str, &str> {
-        let (input, r) = opt(tag("; tunnel leads to valve "))(input)?;
-        if r.is_some() {
-            return Ok((input, r.unwrap()));
-        }
-
-        let (input, plural) = opt(tag("; tunnels lead to valves "))(input)?;
-        return Ok((input, plural.unwrap()));
+        alt((
+            parse::token("; tunnel leads to valve"),
+            parse::token("; tunnels lead to valves")
+        ))(input)
     }
 
     fn parse_leads_to_valves(input: &str) -> IResult<&str, Vec<&str>> {
-    
-        if !input.contains(",") {
-            return Ok((input, vec![input.trim()]));
-        }
-        let splits = input.split(",");
-        let end_result = splits.map(|f| { 
-            f.trim()
-        }).collect_vec();
+        parse::comma_separated(alpha1)(input)
+    }
+
+}
 
-    
-        Ok((input, end_result))
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::{get_valves, submasks, ValveNetwork, MAX_VALVES_WITH_FLOW, START_VALVE};
+
+    const EXAMPLE: &str = "Valve AA has flow rate=0; tunnels lead to valves DD, II, BB
+Valve BB has flow rate=13; tunnels lead to valves CC, AA
+Valve CC has flow rate=2; tunnels lead to valves DD, BB
+Valve DD has flow rate=20; tunnels lead to valves CC, AA, EE
+Valve EE has flow rate=3; tunnels lead to valves FF, DD
+Valve FF has flow rate=0; tunnels lead to valves EE, GG
+Valve GG has flow rate=0; tunnels lead to valves FF, HH
+Valve HH has flow rate=22; tunnel leads to valve GG
+Valve II has flow rate=0; tunnels lead to valves AA, JJ
+Valve JJ has flow rate=21; tunnel leads to valve II";
+
+    #[test]
+    fn submasks_cover_every_subset() {
+        let mut subsets: Vec<usize> = submasks(0b1010).collect();
+        subsets.sort();
+        assert_eq!(subsets, vec![0b0000, 0b0010, 0b1000, 0b1010]);
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn team_of_agents_matches_example() {
+        let network = ValveNetwork::new(&get_valves(EXAMPLE.to_string()), START_VALVE).unwrap();
+
+        assert_eq!(network.max_pressure(30, 1), 1651);
+        assert_eq!(network.max_pressure(26, 2), 1707);
+        assert_eq!(network.max_pressure(26, 3), 1794);
+        assert_eq!(network.max_pressure(30, 0), 0);
+    }
+
+    #[test]
+    fn short_time_and_unreachable_valves_release_nothing() {
+        let network = ValveNetwork::new(&get_valves(EXAMPLE.to_string()), START_VALVE).unwrap();
+        assert_eq!(network.max_pressure(1, 2), 0);
+        assert_eq!(network.max_pressure(-3, 2), 0);
+
+        let cut_off = "Valve AA has flow rate=0; tunnel leads to valve BB
+Valve BB has flow rate=5; tunnel leads to valve AA
+Valve CC has flow rate=40; tunnel leads to valve CC";
+        let network = ValveNetwork::new(&get_valves(cut_off.to_string()), START_VALVE).unwrap();
+        assert_eq!(network.max_pressure(30, 2), 5 * 28);
+    }
+
+    #[test]
+    fn too_many_valves_is_an_error() {
+        // AA leads round a ring of `count` valves that each release a little.
+        let name = |i: usize| format!("{}{}", (b'A' + (i / 26) as u8) as char, (b'A' + (i % 26) as u8) as char);
+        let ring = |count: usize| {
+            (0..=count)
+                .map(|i| format!("Valve {} has flow rate={}; tunnel leads to valve {}", name(i), i.min(1), name((i + 1) % (count + 1))))
+                .join("\n")
+        };
+
+        assert!(ValveNetwork::new(&get_valves(ring(MAX_VALVES_WITH_FLOW)), START_VALVE).is_ok());
+        assert!(ValveNetwork::new(&get_valves(ring(MAX_VALVES_WITH_FLOW + 1)), START_VALVE).is_err());
+    }
+}
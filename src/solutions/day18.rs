@@ -1,10 +1,11 @@
-use std::cmp::{max, min};
 use std::collections::HashSet;
 use std::fmt::Display;
 use std::str::FromStr;
 use std::vec;
-use std::{ops::{Add, Sub}, fmt};
 use itertools::Itertools;
+
+use crate::domain::{bounds::Bounds3D, vertex::Vertex};
+
 pub const NORTH: Vertex = Vertex { x: 0, y: 1, z: 0};
 pub const SOUTH: Vertex = Vertex { x: 0, y: -1, z: 0};
 pub const EAST:Vertex = Vertex { x: 1, y: 0, z: 0 };
@@ -44,60 +45,16 @@ pub fn part_two(input: String) -> impl Display {
     }
     ).collect_vec();
 
-    let vertices = shapes.iter().map(|p| p.min_vertex).collect();
+    let vertices: HashSet<Vertex> = shapes.iter().map(|p| p.min_vertex).collect();
 
-    let outside = BoundingBox::new(&vertices).outside_points(&vertices);
+    let bounds = Bounds3D::from_points(vertices.iter().copied()).unwrap().expand(1);
+    let outside = outside_points(&bounds, &vertices);
     vertices.iter().map(|p| p.sides_touching(&outside)).sum::<i32>()
 }
 
 fn parse_number(input: &str) -> i32 {
     i32::from_str(input).unwrap()
 }
-#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
-pub struct Vertex {
-    x: i32,
-    y: i32,
-    z: i32
-}
-
-impl Vertex {
-    fn new(x: i32, y: i32, z: i32) -> Self {
-        return Vertex { x, y, z };
-    }
-
-    fn neighbors(&self) -> HashSet<Vertex> {
-        return HashSet::from([
-            Vertex { x: self.x - 1, ..*self }, Vertex { x: self.x + 1, ..*self },
-            Vertex { y: self.y - 1, ..*self }, Vertex { y: self.y + 1, ..*self },
-            Vertex { z: self.z - 1, ..*self }, Vertex { z: self.z + 1, ..*self }
-        ]);
-    }
-    fn sides_touching(&self, other: &HashSet<Vertex>) -> i32 {
-        return self.neighbors().iter().filter(|p| other.contains(&p)).count() as i32;
-    }
-}
-
-impl Add for Vertex {
-    type Output = Self;
-
-    fn add(self, other: Self) -> Self {
-        Self {x: self.x + other.x, y: self.y + other.y, z: self.z + other.z}
-    }
-}
-
-impl Sub for Vertex {
-    type Output = Self;
-
-    fn sub(self, other: Self) -> Self {
-        Self {x: self.x - other.x, y: self.y - other.y, z: self.z - other.z}
-    }
-}
-
-impl fmt::Display for Vertex {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "x: {}, y: {}, z: {}", self.x, self.y, self.z)
-    }
-}
 #[derive(Clone)]
 #[allow(dead_code)]
 struct Shape {
@@ -162,38 +119,16 @@ impl Shape {
 
 
 
-#[derive(Debug)]
-struct BoundingBox { maxx: i32, minx: i32, maxy: i32, miny: i32, maxz: i32, minz: i32 }
-
-impl BoundingBox {
-    fn new(points: &HashSet<Vertex>) -> BoundingBox {
-        return BoundingBox {
-            maxx: points.iter().fold(i32::MIN, |acc, a| max(acc, a.x)) + 1,
-            minx: points.iter().fold(i32::MAX, |acc, a| min(acc, a.x)) - 1,
-            maxy: points.iter().fold(i32::MIN, |acc, a| max(acc, a.y)) + 1,
-            miny: points.iter().fold(i32::MAX, |acc, a| min(acc, a.y)) - 1,
-            maxz: points.iter().fold(i32::MIN, |acc, a| max(acc, a.z)) + 1,
-            minz: points.iter().fold(i32::MAX, |acc, a| min(acc, a.z)) - 1
-        }
-    }
-
-    fn inside(&self, p: Vertex) -> bool {
-        return p.x >= self.minx && p.x <= self.maxx &&
-            p.y >= self.miny && p.y <= self.maxy &&
-            p.z >= self.minz && p.z <= self.maxz;            
-    }
-    
-    fn outside_points(&self, solid: &HashSet<Vertex>) -> HashSet<Vertex> {
-        let mut res = HashSet::new();
-        let mut work = Vec::from([Vertex { x: self.minx, y: self.miny, z: self.minz }]);
-        while let Some(p) = work.pop() {
-            if !solid.contains(&p) && !res.contains(&p) && self.inside(p) {
-                res.insert(p);
-                p.neighbors().iter().for_each(|n| work.push(*n));
-            }            
-        }
-        return res;
+fn outside_points(bounds: &Bounds3D, solid: &HashSet<Vertex>) -> HashSet<Vertex> {
+    let mut res = HashSet::new();
+    let mut work = Vec::from([bounds.min]);
+    while let Some(p) = work.pop() {
+        if !solid.contains(&p) && !res.contains(&p) && bounds.contains(p) {
+            res.insert(p);
+            p.neighbors().iter().for_each(|n| work.push(*n));
+        }            
     }
+    return res;
 }
 
 #[cfg(test)]
@@ -1,71 +1,28 @@
-use crate::tools;
+use crate::tools::{memo::Memo, parse, verbosity};
 use itertools::Itertools;
-use nom::{bytes::complete::take_till, IResult};
+use nom::IResult;
 use std::{
     fmt::{self, Display},
-    ops::{Add, Sub, AddAssign, SubAssign},
-    vec,
+    ops::{Add, Sub, Mul, AddAssign, SubAssign},
 };
 
 pub fn part_one(input: String) -> impl Display {
-    let blueprints = parse_blueprints(input);
-
-    let mut quality_sum = 0;        
     const MINUTES_TOTAL: i32 = 24;
 
-    blueprints.iter().for_each(|bp| {
-
-        let operation = Operation {
-            clay_robots: 0,
-            geode_robots: 0,
-            obsidian_robots: 0,
-            ore_robots: 1,
-            next_model: None,
-            supplies: Supplies { ore: 0, clay: 0, obsidian: 0, geodes: 0 }
-        };
-        let strat = optimize(
-            &bp,
-            MINUTES_TOTAL,
-            operation,
-            MINUTES_TOTAL,
-        );
-
-        println!("Blueprint Id: {}", bp.id);
-        println!("  Geodes Cracked: {}", strat);
-        quality_sum = quality_sum + (strat * bp.id);
-    });
-
-    quality_sum
+    parse_blueprints(input)
+        .iter()
+        .map(|bp| bp.id * report(bp, optimize(bp, MINUTES_TOTAL), MINUTES_TOTAL).geodes)
+        .sum::<i32>()
 }
 
 pub fn part_two(input: String) -> impl Display {
-    let blueprints = parse_blueprints(input);
-
-    let mut quality_sum = 1;
     const MINUTES_TOTAL: i32 = 32;
 
-    blueprints.iter().take(3).for_each(|bp| {
-        let operation = Operation {
-            clay_robots: 0,
-            geode_robots: 0,
-            obsidian_robots: 0,
-            ore_robots: 1,
-            next_model: None,
-            supplies: Supplies { ore: 0, clay: 0, obsidian: 0, geodes: 0 }
-        };
-        let strat = optimize(
-            &bp,
-            MINUTES_TOTAL,
-            operation,
-            MINUTES_TOTAL,
-        );
-
-        println!("Strategy for Id {}", bp.id);
-        println!("  Geodes Cracked: {}", strat);
-        quality_sum = quality_sum * strat;
-    });
-
-    quality_sum
+    parse_blueprints(input)
+        .iter()
+        .take(3)
+        .map(|bp| report(bp, optimize(bp, MINUTES_TOTAL), MINUTES_TOTAL).geodes)
+        .product::<i32>()
 }
 
 fn parse_blueprints(input: String) -> Vec<Blueprint> {
@@ -75,116 +32,163 @@ fn parse_blueprints(input: String) -> Vec<Blueprint> {
         .collect_vec()
 }
 
-fn optimize(
-    blueprint: &Blueprint,
-    minutes_left: i32,
-    mut operation: Operation,
-    start_minutes: i32,
-) -> i32 {
-    
-    let mut max_geode = 0;
-    let models = vec![
-        RobotModel::Geode,
-        RobotModel::Obsidian,
-        RobotModel::Ore,
-        RobotModel::Clay,
-    ];
-
-    let mut minutes_left = minutes_left;
-    let harvesting_robots = operation.clone();
-
-    while operation.next_model.is_some() && minutes_left > 0 {
-        minutes_left = minutes_left - 1;
-
-        match operation.next_model {
-            Some(model) => {
-                if blueprint.can_afford(model, operation.supplies) {
-                    match model {
-                        RobotModel::Ore => operation.ore_robots = operation.ore_robots + 1,
-                        RobotModel::Clay => operation.clay_robots = operation.clay_robots + 1,
-                        RobotModel::Obsidian => operation.obsidian_robots = operation.obsidian_robots + 1,
-                        RobotModel::Geode => operation.geode_robots = operation.geode_robots + 1,
-                    }
-
-                    operation.supplies -= blueprint.get_cost_for_model(model);
-                    operation.next_model = None;
-                }
-            }
-            None => (),
-        }
+fn report(blueprint: &Blueprint, schedule: Schedule, minutes: i32) -> Schedule {
+    if verbosity::is_debug() {
+        let verified = match blueprint.replay(&schedule.builds, minutes) {
+            Some(geodes) if geodes == schedule.geodes => "verified".to_string(),
+            Some(geodes) => format!("replay cracks {} instead", geodes),
+            None => "replay can't afford a build".to_string(),
+        };
+        println!("Blueprint {}: {} geodes ({})", blueprint.id, schedule.geodes, verified);
+        schedule.builds.iter().for_each(|build| println!("  {}", build));
+    }
 
-        let gathered = Supplies::new(
-            harvesting_robots.ore_robots,
-            harvesting_robots.clay_robots,
-            harvesting_robots.obsidian_robots,
-            harvesting_robots.geode_robots,
-        );
-        
-        operation.supplies += gathered;
+    schedule
+}
+
+/// Finds the build order that cracks the most geodes in `minutes`.
+fn optimize(blueprint: &Blueprint, minutes: i32) -> Schedule {
+    let mut search = Search {
+        blueprint,
+        minutes,
+        best: Schedule { geodes: 0, builds: Vec::new() },
+        builds: Vec::new(),
+        seen: Memo::new()
+    };
+    search.branch(Operation::start(), minutes);
+
+    if verbosity::is_trace() {
+        println!("Blueprint {} search: {}", blueprint.id, search.seen.stats());
     }
-    
-    max_geode = max_geode.max(operation.supplies.geodes);
-    if minutes_left > 0 {
-        for model in models.iter() {
-            if minutes_left < 1 || (minutes_left < 4 && !matches!(model, RobotModel::Geode))
-            {
-                continue;
-            }
 
-            if(matches!(model, RobotModel::Geode) && operation.obsidian_robots == 0) {
-                continue;
-            }
+    search.best
+}
 
-            if(matches!(model, RobotModel::Obsidian) && operation.clay_robots == 0) {
-                continue;
-            }
+struct Search<'a> {
+    blueprint: &'a Blueprint,
+    minutes: i32,
+    best: Schedule,
+    builds: Vec<Build>,
+    /// The most geodes already cracked on reaching each state, with the geodes left out of the key.
+    seen: Memo<(Operation, i32), i32>
+}
 
-            if matches!(model, RobotModel::Ore)
-                && blueprint.max_ore_cost_per_turn <= operation.ore_robots
-                || matches!(model, RobotModel::Clay)
-                    && blueprint.max_clay_per_turn <= operation.clay_robots
-                || matches!(model, RobotModel::Obsidian)
-                    && blueprint.max_obsidian_per_turn <= operation.obsidian_robots
-            {
-                continue;
-            }
+impl<'a> Search<'a> {
+    // Each branch picks the next robot to build and skips straight to the minute it can be afforded,
+    // so no branch ever waits around without a plan.
+    fn branch(&mut self, operation: Operation, minutes_left: i32) {
+        let idle_geodes = operation.supplies.geodes + operation.geode_robots * minutes_left;
+        if idle_geodes > self.best.geodes {
+            self.best = Schedule { geodes: idle_geodes, builds: self.builds.clone() };
+        }
+        if optimistic_geodes(&operation, minutes_left) <= self.best.geodes {
+            return;
+        }
 
-            if (start_minutes - minutes_left) > 20 && matches!(model, RobotModel::Ore) {
+        // Everything from here on plays out the same as last time, just with fewer geodes in hand.
+        let mut state = operation;
+        state.supplies.geodes = 0;
+        if self.seen.get(&(state, minutes_left)).is_some_and(|geodes| geodes >= operation.supplies.geodes) {
+            return;
+        }
+        self.seen.insert((state, minutes_left), operation.supplies.geodes);
+
+        for model in [RobotModel::Geode, RobotModel::Obsidian, RobotModel::Clay, RobotModel::Ore] {
+            if operation.robots(model) >= self.blueprint.max_useful_robots(model) {
                 continue;
             }
 
-            if (start_minutes - minutes_left) > 21 && matches!(model, RobotModel::Clay) {
+            let Some(wait) = self.blueprint.minutes_until_affordable(model, &operation) else {
+                continue;
+            };
+            // The robot only starts collecting the minute after it is built.
+            let minutes_after_build = minutes_left - wait - 1;
+            if minutes_after_build <= 0 {
                 continue;
             }
 
-            let mut next_opertation = operation.clone();
-            next_opertation.next_model = Some(*model);
+            let mut next = operation;
+            next.supplies += operation.production() * (wait + 1);
+            next.supplies -= self.blueprint.get_cost_for_model(model);
+            next.add_robot(model);
 
-            let strat = optimize(
-                blueprint,
-                minutes_left,
-                next_opertation,
-                start_minutes,
-            );
-
-            max_geode = max_geode.max(strat);
+            self.builds.push(Build { minute: self.minutes - minutes_left + wait + 1, model });
+            self.branch(next, minutes_after_build);
+            self.builds.pop();
         }
     }
-    
-    return max_geode;
 }
 
-#[derive(Debug, Hash, Clone, Copy)]
+// The geodes we'd end with if a geode robot could be built every minute from now on.
+fn optimistic_geodes(operation: &Operation, minutes_left: i32) -> i32 {
+    operation.supplies.geodes + operation.geode_robots * minutes_left + minutes_left * (minutes_left - 1) / 2
+}
+
+/// The most geodes a blueprint can crack, and the robots built to get there.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Schedule {
+    geodes: i32,
+    builds: Vec<Build>
+}
+
+/// A robot that is paid for and built during `minute`, counting from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Build {
+    minute: i32,
+    model: RobotModel
+}
+
+impl fmt::Display for Build {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "minute {:>2}: {:?} robot", self.minute, self.model)
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
 struct Operation {
     ore_robots: i32,
     clay_robots: i32,
     obsidian_robots: i32,
     geode_robots: i32,
-    next_model: Option<RobotModel>,
     supplies: Supplies
 }
 
-#[derive(Debug, Hash, Clone, Copy)]
+impl Operation {
+    fn start() -> Self {
+        Operation {
+            ore_robots: 1,
+            clay_robots: 0,
+            obsidian_robots: 0,
+            geode_robots: 0,
+            supplies: Supplies::new(0, 0, 0, 0)
+        }
+    }
+
+    fn robots(&self, model: RobotModel) -> i32 {
+        match model {
+            RobotModel::Ore => self.ore_robots,
+            RobotModel::Clay => self.clay_robots,
+            RobotModel::Obsidian => self.obsidian_robots,
+            RobotModel::Geode => self.geode_robots,
+        }
+    }
+
+    fn add_robot(&mut self, model: RobotModel) {
+        match model {
+            RobotModel::Ore => self.ore_robots += 1,
+            RobotModel::Clay => self.clay_robots += 1,
+            RobotModel::Obsidian => self.obsidian_robots += 1,
+            RobotModel::Geode => self.geode_robots += 1,
+        }
+    }
+
+    // What the robots collect in one minute.
+    fn production(&self) -> Supplies {
+        Supplies::new(self.ore_robots, self.clay_robots, self.obsidian_robots, self.geode_robots)
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
 struct Supplies {
     ore: i32,
     clay: i32,
@@ -239,6 +243,14 @@ impl Sub for Supplies {
         }
     }
 }
+impl Mul<i32> for Supplies {
+    type Output = Self;
+
+    fn mul(self, times: i32) -> Self {
+        Supplies::new(self.ore * times, self.clay * times, self.obsidian * times, self.geodes * times)
+    }
+}
+
 impl Supplies {
     fn new(ore: i32, clay: i32, obsidian: i32, geodes: i32) -> Self {
         Supplies {
@@ -267,7 +279,7 @@ impl fmt::Display for Supplies {
     }
 }
 
-#[derive(Debug, Hash, Clone, Copy)]
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
 enum RobotModel {
     Ore,
     Clay,
@@ -287,13 +299,10 @@ struct Blueprint {
 }
 impl Blueprint {
     fn parse(input: &str) -> IResult<&str, Self> {
-        let (input, id) = Blueprint::get_next_number(input)?;
-        let (input, ore_cost) = Blueprint::get_next_number(input)?;
-        let (input, clay_ore_cost) = Blueprint::get_next_number(input)?;
-        let (input, obsidian_ore_cost) = Blueprint::get_next_number(input)?;
-        let (input, obsidian_clay_cost) = Blueprint::get_next_number(input)?;
-        let (input, geode_ore_cost) = Blueprint::get_next_number(input)?;
-        let (input, geode_obsidian_cost) = Blueprint::get_next_number(input)?;
+        let (input, numbers) = parse::n_integers::<i32>(7)(input)?;
+        let [id, ore_cost, clay_ore_cost, obsidian_ore_cost, obsidian_clay_cost, geode_ore_cost, geode_obsidian_cost] = numbers[..] else {
+            unreachable!()
+        };
         let max_ore_use_per_turn =
             ore_cost.max(clay_ore_cost.max(obsidian_ore_cost.max(geode_ore_cost)));
         let max_clay_per_turn = obsidian_clay_cost;
@@ -313,6 +322,53 @@ impl Blueprint {
         return Ok((input, bp));
     }
 
+    /// A factory builds one robot a minute, so more robots of a kind than the most any
+    /// robot costs of that resource can never be put to use. Geode robots are always useful.
+    fn max_useful_robots(&self, model: RobotModel) -> i32 {
+        match model {
+            RobotModel::Ore => self.max_ore_cost_per_turn,
+            RobotModel::Clay => self.max_clay_per_turn,
+            RobotModel::Obsidian => self.max_obsidian_per_turn,
+            RobotModel::Geode => i32::MAX,
+        }
+    }
+
+    /// Minutes of collecting before the robot can be paid for, or `None` if nothing
+    /// is collecting a resource it needs.
+    fn minutes_until_affordable(&self, model: RobotModel, operation: &Operation) -> Option<i32> {
+        let cost = self.get_cost_for_model(model);
+        let stock = operation.supplies;
+        let income = operation.production();
+
+        [(cost.ore, stock.ore, income.ore), (cost.clay, stock.clay, income.clay), (cost.obsidian, stock.obsidian, income.obsidian)]
+            .into_iter()
+            .map(|(needed, have, per_minute)| match (needed - have).max(0) {
+                0 => Some(0),
+                _ if per_minute == 0 => None,
+                short => Some((short + per_minute - 1) / per_minute),
+            })
+            .try_fold(0, |wait, resource_wait| Some(wait.max(resource_wait?)))
+    }
+
+    /// Plays a schedule minute by minute and returns the geodes it cracks, or `None`
+    /// if a robot is built before it can be afforded.
+    fn replay(&self, builds: &[Build], minutes: i32) -> Option<i32> {
+        let mut operation = Operation::start();
+        for minute in 1..=minutes {
+            let production = operation.production();
+            if let Some(build) = builds.iter().find(|b| b.minute == minute) {
+                if !self.can_afford(build.model, operation.supplies) {
+                    return None;
+                }
+                operation.supplies -= self.get_cost_for_model(build.model);
+                operation.add_robot(build.model);
+            }
+            operation.supplies += production;
+        }
+
+        Some(operation.supplies.geodes)
+    }
+
     fn can_afford(&self, model: RobotModel, current_supplies: Supplies) -> bool {
         match model {
             RobotModel::Ore => current_supplies.can_cover_cost(self.ore_robot_cost),
@@ -330,9 +386,31 @@ impl Blueprint {
             RobotModel::Geode => self.geode_robot_cost,
         }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{optimize, parse_blueprints};
+
+    const EXAMPLE: &str = "Blueprint 1: Each ore robot costs 4 ore. Each clay robot costs 2 ore. Each obsidian robot costs 3 ore and 14 clay. Each geode robot costs 2 ore and 7 obsidian.
+Blueprint 2: Each ore robot costs 2 ore. Each clay robot costs 3 ore. Each obsidian robot costs 3 ore and 8 clay. Each geode robot costs 3 ore and 12 obsidian.";
+
+    #[test]
+    fn optimizer_finds_example_geodes_with_a_valid_schedule() {
+        let blueprints = parse_blueprints(EXAMPLE.to_string());
+        let schedules = blueprints.iter().map(|bp| optimize(bp, 24)).collect::<Vec<_>>();
+
+        assert_eq!(schedules.iter().map(|s| s.geodes).collect::<Vec<_>>(), vec![9, 12]);
+        for (bp, schedule) in blueprints.iter().zip(schedules.iter()) {
+            assert_eq!(bp.replay(&schedule.builds, 24), Some(schedule.geodes));
+        }
+    }
+
+    #[test]
+    fn longer_runs_match_example() {
+        let blueprints = parse_blueprints(EXAMPLE.to_string());
 
-    fn get_next_number(input: &str) -> IResult<&str, i32> {
-        let (input, _) = (take_till(|c| tools::is_digit(c)))(input)?;
-        (tools::parse_numbers)(input)
+        assert_eq!(optimize(&blueprints[0], 32).geodes, 56);
+        assert_eq!(optimize(&blueprints[1], 32).geodes, 62);
     }
 }
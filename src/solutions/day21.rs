@@ -1,65 +1,60 @@
-use core::panic;
 use std::{fmt::Display, collections::HashMap};
 
 use itertools::Itertools;
 use nom::{bytes::{complete::{take_until, tag}, streaming::take}, IResult, branch::alt, character::complete::{one_of, space1}, sequence::{tuple, preceded}};
-use crate::tools::parse_numbers_i64;
+use crate::{domain::expression::{self, Equation, Expression, Operator}, tools::{parse, verbosity}};
 
 pub fn part_one(input: String) -> impl Display {
     let monkies = parse_monkies(input);
-    monkey_math(monkies)
+    let jobs = monkey_jobs(&monkies);
+
+    let value = build_expression(&jobs, "root", None).fold().evaluate(&HashMap::new()).unwrap();
+    expression::to_i64(&value).expect("root should yell a whole number")
 }
 
 pub fn part_two(input: String) -> i64 {
     let monkies = parse_monkies(input);
-    let mut monkey_solutions = HashMap::new();
-    fill_solutions(&mut monkey_solutions, &monkies, "root".to_string());
-
-    let monkey_map:HashMap<&str, MonkeyJob> = monkies.iter().map(|m| (m.id.as_str(), m.job.clone())).collect();
+    let jobs = monkey_jobs(&monkies);
 
-    let (mut unknown, mut result, mut correction) = ("root".to_string(), 0, -1);
-    while unknown != "humn" {
-        let MonkeyJob::Operation(left, op, right) = monkey_map[unknown.as_str()].clone() else {
-            panic!();
-        };
+    let MonkeyJob::Operation(left, _, right) = jobs["root"] else {
+        panic!("root should be waiting on two monkeys");
+    };
 
-        (unknown, result) = match (monkey_solutions.get(&left), monkey_solutions.get(&right)) {
-            (None, Some(&val)) => (left, op.solve_for_left(result, val)),
-            (Some(&val), None) => (right, op.solve_for_right(result, val)),
-            _ => panic!(),
-        };
+    let equation = Equation::new(
+        build_expression(&jobs, left, Some(HUMAN)),
+        build_expression(&jobs, right, Some(HUMAN))
+    ).fold();
 
-        // Change the result of the root equation to be negative
-        result *= correction;
-        correction = 1;
+    if verbosity::is_debug() {
+        println!("{}", equation);
     }
 
-    result
+    let answer = equation.solve_for(HUMAN).unwrap_or_else(|e| panic!("Can't work out what to yell: {}", e));
+    expression::to_i64(&answer).unwrap_or_else(|| panic!("{} isn't something a human can yell", answer))
 }
 
-fn fill_solutions(monkey_solutions: &mut HashMap<String, i64>, monkies: &Vec<Monkey>, monkey: String) -> Option<i64> {
-    if monkey == "humn" {
-        return None;
-    }
-
-    let val = match monkies.iter().find(|x| x.id == monkey) {
-        Some(found_monkey) => {
-            match found_monkey.job.clone() {
-                MonkeyJob::Number(num) => num,
-                MonkeyJob::Operation(left_monkey, operator, right_monkey) => {
-                    let left = fill_solutions(monkey_solutions, monkies, left_monkey);
-                    let right = fill_solutions(monkey_solutions, monkies, right_monkey);
-                    operator.eval(left?, right?)
-                },
-            }
-        }
-        _ => panic!("Where my monkey at?"),
-    };
+const HUMAN: &str = "humn";
 
-    monkey_solutions.insert(monkey, val);
-    Some(val)
+fn monkey_jobs(monkies: &[Monkey]) -> HashMap<&str, &MonkeyJob> {
+    monkies.iter().map(|m| (m.id.as_str(), &m.job)).collect()
 }
 
+// The job of `monkey` with every monkey it waits on expanded. The `unknown` monkey stays a variable.
+fn build_expression(jobs: &HashMap<&str, &MonkeyJob>, monkey: &str, unknown: Option<&str>) -> Expression {
+    if Some(monkey) == unknown {
+        return Expression::variable(monkey);
+    }
+
+    match jobs.get(monkey) {
+        Some(MonkeyJob::Number(num)) => Expression::constant(*num),
+        Some(MonkeyJob::Operation(left, operator, right)) => Expression::binary(
+            build_expression(jobs, left, unknown),
+            *operator,
+            build_expression(jobs, right, unknown)
+        ),
+        None => panic!("Where my monkey at?"),
+    }
+}
 
 fn parse_monkies(input: String) -> Vec<Monkey> {
     input
@@ -69,23 +64,6 @@ fn parse_monkies(input: String) -> Vec<Monkey> {
         ).collect_vec()
 }
 
-fn monkey_math(monkies: Vec<Monkey>) -> i64 {
-    let mut monkey_solutions:HashMap<String, i64> = HashMap::new();
-    while !monkey_solutions.contains_key("root") {
-        let remaining_monkies = monkies.iter().filter(|p| !monkey_solutions.contains_key(&p.id)).collect_vec();
-
-        for monkey in remaining_monkies {
-            let possible_solution = monkey.try_get_solution(&monkey_solutions);
-            if let Some(num) = possible_solution {
-                monkey_solutions.insert(monkey.id.clone(), num);
-            }
-        }
-    }
-
-    return monkey_solutions["root"].clone();
-    
-}
-
 
 #[derive(Debug, Clone)]
 struct Monkey {
@@ -106,7 +84,7 @@ impl Monkey {
     }
 
     fn parse_yell(input: &str) -> IResult<&str, MonkeyJob> { 
-        let (input, num) = (preceded(tag(": "), parse_numbers_i64))(input)?;
+        let (input, num) = (preceded(tag(": "), parse::signed::<i64>))(input)?;
         Ok((input, MonkeyJob::Number(num)))
     }
 
@@ -128,18 +106,6 @@ impl Monkey {
         };
         Ok((input, job))
     }
-
-    fn try_get_solution(&self, monkey_solutions: &HashMap<String, i64>) -> Option<i64> {
-        match &self.job {
-            MonkeyJob::Operation(l, o, r) => {
-                if !monkey_solutions.contains_key(l) || !monkey_solutions.contains_key(r) {
-                    return Option::None;
-                }
-                Some(o.eval(monkey_solutions[l], monkey_solutions[r]))
-            }
-            MonkeyJob::Number(num) => Some(*num),
-        }
-    }
 }
 
 
@@ -148,38 +114,45 @@ enum MonkeyJob {
     Number(i64),
     Operation(String, Operator, String)
 }
-#[derive(Debug, Clone, Copy)]
-enum Operator {
-    Add,
-    Subtract,
-    Multiply,
-    Divide,
-}
 
-impl Operator {
-    fn eval(self, left: i64, right: i64) -> i64 {
-        match self {
-            Operator::Add => left + right,
-            Operator::Subtract => left - right,
-            Operator::Multiply => left * right,
-            Operator::Divide => left / right,
-        }
-    }
-    fn solve_for_left(self, result: i64, right: i64) -> i64 {
-        match self {
-            Operator::Add => result - right,
-            Operator::Subtract => result + right,
-            Operator::Multiply => result / right,
-            Operator::Divide => result * right,
-        }
+#[cfg(test)]
+mod tests {
+    use super::{part_one, part_two};
+
+    const EXAMPLE: &str = "root: pppw + sjmn
+dbpl: 5
+cczh: sllz + lgvd
+zczc: 2
+ptdq: humn - dvpt
+dvpt: 3
+lfqf: 4
+humn: 5
+ljgn: 2
+sjmn: drzm * dbpl
+sllz: 4
+pppw: cczh / lfqf
+lgvd: ljgn * ptdq
+drzm: hmdt - zczc
+hmdt: 32";
+
+    #[test]
+    fn monkeys_match_example() {
+        assert_eq!(part_one(EXAMPLE.to_string()).to_string(), "152");
+        assert_eq!(part_two(EXAMPLE.to_string()), 301);
     }
 
-    fn solve_for_right(self, result: i64, left: i64) -> i64 {
-        match self {
-            Operator::Add => result - left,
-            Operator::Subtract => left - result,
-            Operator::Multiply => result / left,
-            Operator::Divide => left / result,
-        }
+    #[test]
+    fn halves_along_the_way_still_give_whole_answers() {
+        // humn / 2 is 1.5 when yelling 3, and 2.5 for the answer to part two.
+        let halves = "root: pppp + qqqq
+pppp: xxxx * ffff
+xxxx: humn / dddd
+dddd: 2
+ffff: 4
+humn: 3
+qqqq: 10";
+
+        assert_eq!(part_one(halves.to_string()).to_string(), "16");
+        assert_eq!(part_two(halves.to_string()), 5);
     }
 }
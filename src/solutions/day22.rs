@@ -1,186 +1,166 @@
-use std::{collections::{HashMap, HashSet}, fmt::Display, ops::{Add, Sub}};
-use nom::{IResult, character::{complete::{alpha1}}, multi::{many0}, branch::alt};
+use std::{collections::{HashMap, HashSet}, fmt::{self, Display}, fs, path::PathBuf};
+use itertools::Itertools;
+use nom::{IResult, character::complete::{multispace0, one_of}, combinator::all_consuming, multi::many0, branch::alt, sequence::terminated};
 use num::integer::Roots;
 
-use crate::{domain::{point::{*}, vertex::Vertex}, tools::{parse_numbers}};
+use crate::{domain::{bounds::Bounds, point::{*}, vertex::Vertex}, tools::{options, parse}, viz::export::{self, Animation, Image, Palette, Rgb}};
 
-static DIRECTIONS: [Point; 4] = [
-    Point { x: 1, y: 0 },
-    Point { x: 0, y: 1 },
-    Point { x: -1, y: 0 },
-    Point { x: 0, y: -1 },
-];
+pub fn part_one(input: String) -> impl Display {
+    let (grid, instructions) = parse_map(input);
+    let (position, direction) = move_around_map(grid, instructions);
 
-#[derive(Debug, Clone, Copy)]
-struct Vector3 {
-    x: f32,
-    y: f32,
-    z: f32
+    password(position, direction)
 }
 
-impl Vector3 {
-pub fn new(x: f32, y: f32, z: f32) -> Self {
-    return Vector3 { x, y, z };
-}
-    
-}
-
-impl From<Vertex> for Vector3 {
-    fn from(p: Vertex) -> Self {
-        Vector3::new(p.x as f32, p.y as f32, p.z as f32)
-    }
-}
-impl From<Vector3> for Vertex {
-    fn from(p: Vector3) -> Self {
-        Vertex::new(p.x.round() as i32, p.y.round() as i32, p.z.round() as i32)
-    }
-}
+pub fn part_two(input: String) -> impl Display {
+    let (mut grid, instructions) = parse_map(input);
+    let cube = Cube::fold(&grid).unwrap();
+    grid.wraps = WrapTable::cube(&grid, &cube);
 
-impl Add for Vector3 {
-    type Output = Self;
+    let (position, direction) = walk_cube(&grid, instructions);
 
-    fn add(self, other: Self) -> Self {
-        Self {x: self.x + other.x, y: self.y + other.y, z: self.z + other.z}
-    }
+    password(position, direction)
 }
 
-impl Sub for Vector3 {
-    type Output = Self;
-
-    fn sub(self, other: Self) -> Self {
-        Self {x: self.x - other.x, y: self.y - other.y, z: self.z - other.z}
-    }
+fn password(position: Point, direction: Direction) -> i32 {
+    (position.y * 1000) + (position.x * 4) + direction.direction_score()
 }
 
+fn walk_cube(grid: &Grid, instructions: Vec<Instruction>) -> (Point, Direction) {
+    dump_edges(grid);
+    let (player, path) = walk(grid, instructions);
+    write_trace("day22_cube_trace", grid, &path);
 
+    if export::is_enabled() {
+        export_walk("day22_cube_walk", grid, &visited(&path));
+    }
 
-pub fn part_one(input: String) -> impl Display {
-    let (grid, instructions) = parse_map(input);
-
-
-    //println!("{:?}", grid);
-    let (position, direction) = move_around_map(grid, instructions);
-
-    (position.y * 1000) + (position.x * 4) + direction.direction_score()
+    (player.position, player.facing)
 }
 
-pub fn part_two(input: String) -> impl Display {
-    let (mut grid, instructions) = parse_map(input);
-    let mut cube = add_cube(&mut grid);
+fn move_around_map(grid: Grid, instructions: Vec<Instruction>) -> (Point, Direction) {
+    dump_edges(&grid);
+    let (player, path) = walk(&grid, instructions);
+    write_trace("day22_flat_trace", &grid, &path);
 
-    let (position, direction) = walk_cube(&grid, &mut cube, instructions);
+    if export::is_enabled() {
+        export_walk("day22_flat_walk", &grid, &visited(&path));
+    }
 
-    (position.y * 1000) + (position.x * 4) + direction.direction_score()
+    return (player.position, player.facing)
 }
 
-fn walk_cube(grid: &Grid, cube: &mut Cube, mut instructions: Vec<Instruction>) -> (Point, Direction) {
-    println!("{}", &grid.get_starting_position());
-    let pos3 = cube
-        .get_3d_pos(&grid.get_starting_position())
-        .ok_or("invalid 3d map").unwrap();
+// Prints the wrap table with `--dump-edges` (or `-o dump-edges=true`).
+fn dump_edges(grid: &Grid) {
+    if !options::get_or("dump-edges", false) {
+        return;
+    }
 
-    let mut cube_walk = cube.clone();
-    let mut visited = Vec::new();
+    print!("{}", grid.wraps);
+    match grid.wraps.validate() {
+        Ok(()) => println!("Every edge is stitched back the way it came"),
+        Err(error) => println!("Invalid edge table: {}", error),
+    }
+}
 
-    let mut player = Player3D { position3D: pos3, facing3D: Direction::East, position2d: grid.get_starting_position()  };
-    while instructions.len() > 0 {
-        let next_ins = instructions.remove(0);
+// Follows the instructions from the starting position. The path has an entry for every step and
+// every turn, with the heading the player has once it is done.
+fn walk(grid: &Grid, instructions: Vec<Instruction>) -> (Player, Vec<(Point, Direction)>) {
+    let start = grid.get_starting_position();
+    let mut player = Player { facing: Direction::East, position: start };
+    let mut path = vec![(start, player.facing)];
 
-        match next_ins {
+    for instruction in instructions {
+        match instruction {
             Instruction::Move(movement) => {
-                let mut remaining = movement;
-
-                while remaining > 0 {
-                    let cube_orig = cube_walk.clone();
-
-                    let mut next_pos3 = player.position3D + player.facing3D.to_vertex();
-                    match next_pos3 {
-                        p if p.x < 0 => {
-                            cube.rotate(rad(90), 0.0, 0.0);
-                            next_pos3.x = grid.side_len - 1;
-                        }
-                        p if p.x >= grid.side_len => {
-                            cube.rotate(rad(-90), 0.0, 0.0);
-                            next_pos3.x = 0;
-                        }
-                        p if p.y < 0 => {
-                            cube.rotate(0.0, rad(-90), 0.0);
-                            next_pos3.y = grid.side_len - 1;
-                        }
-                        p if p.y >= grid.side_len => {
-                            cube.rotate(0.0, rad(90), 0.0);
-                            next_pos3.y = 0;
-                        }
-                        _ => (),
-                    };
-
-                    let pos2 = cube.tiles.get(&next_pos3).unwrap();
-
-                    let tile = grid.tiles.get(pos2).unwrap();
-
-                    player.move_to_tile(next_pos3, *tile, cube);
-                    if matches!(tile, Tile::Floor) {
-                        remaining -= 1;
-                        visited.push(player.position2d);
-                    }
-                    else {
-                        remaining = 0;
+                for _ in 0..movement {
+                    let (point, facing, tile) = grid.get_next_tile_in_direction(player.position, player.facing);
+                    if matches!(tile, Tile::Wall) {
+                        break;
                     }
-                    cube_walk = cube_orig;
+                    player.move_to_tile(point, facing);
+                    path.push((point, facing));
                 }
             },
-            Instruction::Rotate(rotation) => { 
-                player.change_facing(rotation); 
-            },
+            Instruction::Rotate(rotation) => {
+                player.change_facing(rotation);
+                path.push((player.position, player.facing));
+            }
         }
-
     }
 
-    let facing = DIRECTIONS
-        .iter()
-        .position(|&p| p == (player.position2d - visited[visited.len() - 2]))
-        .ok_or("case when turning on the last tile is not handled");
+    (player, path)
+}
 
-    println!("{:?}", facing);
-    println!("{:?}",  player.facing3D);
-    (player.position2d, player.facing3D)
+fn visited(path: &[(Point, Direction)]) -> Vec<Point> {
+    path.iter().map(|(p, _)| *p).dedup().collect()
 }
 
+// The board with the path drawn over it the way the puzzle does, each tile showing the last heading it was left with.
+fn render_trace(grid: &Grid, path: &[(Point, Direction)]) -> String {
+    let headings: HashMap<Point, Direction> = path.iter().copied().collect();
+    let bounds = Bounds::from_points(grid.tiles.keys().copied()).unwrap();
+
+    (1..=bounds.max.y)
+        .map(|y| {
+            let row: String = (1..=bounds.max.x)
+                .map(|x| {
+                    let p = Point::new(x, y);
+                    match (headings.get(&p), grid.tiles.get(&p)) {
+                        (Some(heading), _) => heading.arrow(),
+                        (None, Some(Tile::Floor)) => '.',
+                        (None, Some(Tile::Wall)) => '#',
+                        (None, None) => ' ',
+                    }
+                })
+                .collect();
+            row.trim_end().to_string()
+        })
+        .join("\n")
+}
 
-fn move_around_map(grid: Grid, mut instructions: Vec<Instruction>) -> (Point, Direction) {
-    let start = grid.get_starting_position();
-    let mut player = Player { facing: Direction::East, position: start };
+// With `-o trace=-` the path is drawn in the terminal, with `-o trace=DIR` it is written to `DIR/<name>.txt`.
+fn write_trace(name: &str, grid: &Grid, path: &[(Point, Direction)]) {
+    let Some(target) = options::get_raw("trace") else {
+        return;
+    };
 
-    println!("{:?}", player);
+    let trace = render_trace(grid, path);
+    if target == "-" {
+        println!("{}", trace);
+        return;
+    }
 
-    while instructions.len() > 0 {
-        let next_ins = instructions.remove(0);
+    let file = PathBuf::from(target).join(format!("{}.txt", name));
+    match fs::create_dir_all(file.parent().unwrap()).and_then(|_| fs::write(&file, trace + "\n")) {
+        Ok(()) => println!("Wrote {}", file.display()),
+        Err(error) => println!("Could not write {}: {}", file.display(), error),
+    }
+}
 
-        match next_ins {
-            Instruction::Move(movement) => {
-                let mut remaining = movement;
-
-                while remaining > 0 {
-                    let (point, tile) = grid.get_next_tile_in_direction(player.position, player.facing);
-                    //println!("{:?} {:?}", point, tile);
-                    player.move_to_tile(point, tile);
-                    if matches!(tile, Tile::Floor) {
-                        player.move_to_tile(point, tile);
-                        remaining -= 1;
-                    }
-                    else {
-                        remaining = 0;
-                    }
-                }
-            },
-            Instruction::Rotate(rotation) => player.change_facing(rotation),
-        }
+const TRAIL: Rgb = [240, 90, 70];
+const TRAIL_START: Rgb = [80, 200, 255];
+
+// The map with the path drawn over it, as a still and as an animation of the walk so far.
+fn export_walk(name: &str, grid: &Grid, visited: &[Point]) {
+    let bounds = Bounds::from_points(grid.tiles.keys().copied()).unwrap();
+    let draw = |steps: usize| {
+        let trail: HashSet<&Point> = visited[..steps].iter().collect();
+        Image::from_bounds(bounds, true, 2, |p| match p {
+            _ if Some(&p) == visited.first() => TRAIL_START,
+            _ if trail.contains(&p) => TRAIL,
+            _ => grid.tiles.get(&p).color()
+        })
+    };
 
-        //println!("{:?}", player);
+    let mut animation = Animation::new(50);
+    let stride = (visited.len() / 100).max(1);
+    for steps in (stride..visited.len()).step_by(stride).chain([visited.len()]) {
+        animation.push(draw(steps));
     }
 
-    return (player.position, player.facing)
-
+    export::save_image(name, &draw(visited.len()));
+    export::save_animation(name, &animation);
 }
 
 fn parse_map(input: String) -> (Grid, Vec<Instruction>) {
@@ -189,14 +169,13 @@ fn parse_map(input: String) -> (Grid, Vec<Instruction>) {
     
     return (
         Grid::parse(s.next().unwrap()), 
-        parse_instructions(s.next().unwrap()).unwrap().1
+        parse_instructions(s.next().unwrap()).unwrap_or_else(|e| panic!("Can't follow the path: {}", e)).1
     );
 
 }
 
 fn parse_instructions(input: &str) -> IResult<&str,Vec<Instruction>> {
-    many0(parse_instruction)(input)
-    
+    all_consuming(terminated(many0(parse_instruction), multispace0))(input)
 }
 
 fn parse_instruction(input: &str) -> IResult<&str, Instruction> { 
@@ -204,87 +183,21 @@ fn parse_instruction(input: &str) -> IResult<&str, Instruction> {
 }
 
 fn parse_move(input: &str) -> IResult<&str, Instruction> { 
-    let (input, num) = (parse_numbers)(input)?;
+    let (input, num) = (parse::signed::<i32>)(input)?;
     Ok((input, Instruction::Move(num)))
 }
 
 fn parse_rotate(input: &str) -> IResult<&str, Instruction> { 
 
-    let (input, rotate_char) = (alpha1)(input)?;
+    let (input, rotate_char) = (one_of("RL"))(input)?;
     let ins = match rotate_char {
-        "R" => Instruction::Rotate(Rotation::Right),
-        "L" => Instruction::Rotate(Rotation::Left),
-        _ => unreachable!()
-
+        'R' => Instruction::Rotate(Rotation::Right),
+        _ => Instruction::Rotate(Rotation::Left)
     };
     Ok((input, ins))
 }
 
 
-fn add_cube(grid: &mut Grid) -> Cube {
-    // find position of sides within the tile map
-    let mut sides = Vec::new();
-    for y in 0..(grid.height / grid.side_len) {
-        for x in 0..(grid.width / grid.side_len) {
-            let tile_pos = Point::new((x * grid.side_len) + 1, (y * grid.side_len) + 1);
-            println!("{:?}", tile_pos);
-            if grid.tiles.contains_key(&tile_pos) {
-                println!("{:?}", Point::new(x, y));
-                sides.push(Point::new(x, y));
-            }
-        }
-    }
-
-    // map sides to cube
-    let start = sides[0].clone();
-    let sides_map: HashSet<Point> = HashSet::from_iter(sides);
-    let mut cube = Cube {
-        side_len: 50,
-        tiles: HashMap::new()
-    };
-    fill_cube(&start, &sides_map, &mut HashSet::new(), &mut cube, &grid);
-    return cube;
-}
-
-fn rad(deg: i32) -> f32 {
-    (deg as f32).to_radians()
-}
-
-fn fill_cube(
-    side: &Point,
-    sides: &HashSet<Point>,
-    visited: &mut HashSet<Point>,
-    cube: &mut Cube,
-    grid: &Grid
-) {
-    for y in side.y * cube.side_len..side.y * cube.side_len + cube.side_len {
-        for x in side.x * cube.side_len..side.x * cube.side_len + cube.side_len {
-            let from = Point::new(x + 1, y + 1);
-            let to = Vertex::new(
-                x - side.x * cube.side_len,
-                grid.side_len - 1 - (y - side.y * cube.side_len), // flip y
-                1,
-            );
-            cube.tiles.insert(to, from);
-        }
-    }
-
-    for (dir_idx, dir) in DIRECTIONS.iter().enumerate() {
-        let next_side = *side + *dir;
-        if visited.insert(next_side) && sides.contains(&next_side) {
-            match dir_idx {
-                0 | 2 => cube.rotate(rad((dir_idx as i32 - 1) * 90), 0.0, 0.0),
-                _ => cube.rotate(0.0, rad((dir_idx as i32 - 2) * 90), 0.0),
-            }
-            fill_cube(&next_side, sides, visited, cube, grid);
-            match dir_idx {
-                0 | 2 => cube.rotate(rad((dir_idx as i32 - 1) * -90), 0.0, 0.0),
-                _ => cube.rotate(0.0, rad((dir_idx as i32 - 2) * -90), 0.0),
-            }
-        }
-    }
-}
-
 #[derive(Debug)]
 struct Player {
     position: Point,
@@ -292,11 +205,9 @@ struct Player {
 }
 
 impl Player {
-    fn move_to_tile(&mut self, point: Point, tile: Tile) {
-        match tile {
-            Tile::Floor => self.position = point,
-            Tile::Wall => (),
-        }
+    fn move_to_tile(&mut self, point: Point, facing: Direction) {
+        self.position = point;
+        self.facing = facing;
     }
 
     fn change_facing(&mut self, rotation: Rotation) { 
@@ -307,94 +218,280 @@ impl Player {
     }
 }
 
-#[allow(non_snake_case)]
-struct Player3D {
-    position3D: Vertex,
-    facing3D: Direction,
-    position2d: Point
+/// Which way a face of the net ends up pointing once folded. `right` and `down` are where the
+/// net's `x` and `y` axes go and `normal` points out of the cube, so together they are the columns
+/// of an integer rotation matrix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Frame {
+    right: Vertex,
+    down: Vertex,
+    normal: Vertex
 }
 
-impl Player3D {
-    fn move_to_tile(&mut self, pos3d: Vertex, tile: Tile, cube: &Cube) {
-        match tile {
-            Tile::Floor => 
-            { 
-                self.position3D = pos3d;
-                self.position2d = *cube.tiles.get(&pos3d).unwrap(); 
-            },
-            Tile::Wall => (),
+impl Frame {
+    fn flat() -> Self {
+        Frame { right: Vertex::new(1, 0, 0), down: Vertex::new(0, 1, 0), normal: Vertex::new(0, 0, -1) }
+    }
+
+    // The frame of the face next to this one in the net, which folds a quarter turn about the shared edge.
+    fn fold(&self, direction: Direction) -> Self {
+        let Frame { right, down, normal } = *self;
+        match direction {
+            Direction::East => Frame { right: -normal, down, normal: right },
+            Direction::West => Frame { right: normal, down, normal: -right },
+            Direction::South => Frame { right, down: -normal, normal: down },
+            Direction::North => Frame { right, down: normal, normal: -down },
         }
     }
 
-    fn change_facing(&mut self, rotation: Rotation) { 
-        match rotation {
-            Rotation::Left => self.facing3D = self.facing3D.rotate_left(),
-            Rotation::Right => self.facing3D = self.facing3D.rotate_right(),
+    fn direction_vector(&self, direction: Direction) -> Vertex {
+        match direction {
+            Direction::East => self.right,
+            Direction::South => self.down,
+            Direction::West => -self.right,
+            Direction::North => -self.down,
         }
     }
+
+    fn direction_of(&self, vector: Vertex) -> Option<Direction> {
+        [Direction::East, Direction::South, Direction::West, Direction::North]
+            .into_iter()
+            .find(|&direction| self.direction_vector(direction) == vector)
+    }
 }
 
+#[derive(Debug, Clone, Copy)]
+struct Face {
+    /// The face's position in the net, in units of whole faces.
+    cell: Point,
+    frame: Frame
+}
 
+/// The map folded into a cube. Tile centres are placed on a cube spanning `-side_len..=side_len`
+/// on each axis, which keeps every coordinate an integer.
 #[derive(Debug, Clone)]
 struct Cube {
     side_len: i32,
-    tiles: HashMap<Vertex, Point>
+    faces: Vec<Face>
 }
 
 impl Cube {
-    fn get_3d_pos(&self, p: &Point) -> Option<Vertex> {
-        self.tiles.iter().find(|(_, &p2)| p2 == *p).map(|x| *x.0)
-    }
-
-    #[allow(non_snake_case)]
-    fn rotate(&mut self, pitch: f32, roll: f32, yaw: f32) {
-        let cosa = yaw.cos();
-        let sina = yaw.sin();
-        let cosb = pitch.cos();
-        let sinb = pitch.sin();
-        let cosc = roll.cos();
-        let sinc = roll.sin();
-        let Axx = cosa * cosb;
-        let Axy = cosa * sinb * sinc - sina * cosc;
-        let Axz = cosa * sinb * cosc + sina * sinc;
-        let Ayx = sina * cosb;
-        let Ayy = sina * sinb * sinc + cosa * cosc;
-        let Ayz = sina * sinb * cosc - cosa * sinc;
-        let Azx = -sinb;
-        let Azy = cosb * sinc;
-        let Azz = cosb * cosc;
-
-        let t = Vector3::new(
-            (self.side_len as f32 - 1.0) / -2.0,
-            (self.side_len as f32 - 1.0) / -2.0,
-            (self.side_len as f32 - 1.0) / 2.0,
-        );
-
-        // translate
-        let tiles_f = self.tiles.drain().map(|(p, c)| (Vector3::from(p) + t, c));
-
-        // rotate
-        let tiles_f = tiles_f.map(|(p, c)| {
-            let p2 = Vector3::new(
-                Axx * p.x + Axy * p.y + Axz * p.z,
-                Ayx * p.x + Ayy * p.y + Ayz * p.z,
-                Azx * p.x + Azy * p.y + Azz * p.z,
-            );
-            (p2, c)
-        });
-
-        // translate back
-        self.tiles = HashMap::from_iter(tiles_f.map(|(p, c)| (Vertex::from(p - t), c)));
+    /// Folds any of the 11 cube nets by walking outwards from the first face.
+    fn fold(grid: &Grid) -> Result<Cube, String> {
+        let side_len = grid.side_len;
+        let cells: HashSet<Point> = grid.face_cells().into_iter().collect();
+        if cells.len() != 6 || grid.tiles.len() as i32 != 6 * side_len * side_len {
+            return Err(format!("map is not made of 6 faces of {0}x{0} tiles", side_len));
+        }
+
+        let start_cell = grid.face_cell(grid.get_starting_position());
+        let mut faces = vec![Face { cell: start_cell, frame: Frame::flat() }];
+        let mut next = 0;
+        while next < faces.len() {
+            let face = faces[next];
+            for direction in [Direction::East, Direction::South, Direction::West, Direction::North] {
+                let cell = face.cell + direction.to_point();
+                if cells.contains(&cell) && faces.iter().all(|f| f.cell != cell) {
+                    faces.push(Face { cell, frame: face.frame.fold(direction) });
+                }
+            }
+            next += 1;
+        }
+
+        let normals: HashSet<Vertex> = faces.iter().map(|f| f.frame.normal).collect();
+        if faces.len() != 6 || normals.len() != 6 {
+            return Err("map doesn't fold into a cube".to_string());
+        }
+
+        Ok(Cube { side_len, faces })
+    }
+
+    fn face_at(&self, position: Point) -> &Face {
+        let cell = Point::new((position.x - 1) / self.side_len, (position.y - 1) / self.side_len);
+        self.faces.iter().find(|f| f.cell == cell).unwrap()
+    }
+
+    fn to_3d(&self, face: &Face, position: Point) -> Vertex {
+        let n = self.side_len;
+        let column = position.x - 1 - face.cell.x * n;
+        let row = position.y - 1 - face.cell.y * n;
+
+        face.frame.normal * n + face.frame.right * (2 * column + 1 - n) + face.frame.down * (2 * row + 1 - n)
     }
 
+    fn to_2d(&self, face: &Face, vertex: Vertex) -> Point {
+        let n = self.side_len;
+        let column = (vertex.dot(face.frame.right) + n - 1) / 2;
+        let row = (vertex.dot(face.frame.down) + n - 1) / 2;
+
+        Point::new(face.cell.x * n + column + 1, face.cell.y * n + row + 1)
+    }
+
+    /// Where stepping off the edge of a face lands, and which way the player faces afterwards.
+    fn wrap(&self, position: Point, facing: Direction) -> (Point, Direction) {
+        let from = self.face_at(position);
+        let heading = from.frame.direction_vector(facing);
+        let to = self.faces.iter().find(|f| f.frame.normal == heading).unwrap();
+
+        // Over the edge the player keeps heading into the cube, down the side of the face they left.
+        let over_edge = self.to_3d(from, position) + heading - from.frame.normal;
+        let facing = to.frame.direction_of(-from.frame.normal).unwrap();
+
+        (self.to_2d(to, over_edge), facing)
+    }
+}
+
+/// One side of one face of the net.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct FaceEdge {
+    face: Point,
+    side: Direction
+}
+
+impl fmt::Display for FaceEdge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "face ({}, {}) {:?}", self.face.x, self.face.y, self.side)
+    }
+}
+
+/// Walking off the map over `from` comes back in over `to`. Tiles along an edge are counted
+/// left to right or top to bottom, and a `reversed` stitch flips that order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Stitch {
+    from: FaceEdge,
+    to: FaceEdge,
+    reversed: bool
+}
+
+impl Stitch {
+    fn facing(&self) -> Direction {
+        self.to.side.reverse()
+    }
+}
+
+impl fmt::Display for Stitch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} -> {}, facing {:?}", self.from, self.to, self.facing())?;
+        if self.reversed {
+            write!(f, ", reversed")?;
+        }
+        Ok(())
+    }
+}
+
+/// Where every edge of the map that has no face beyond it leads.
+#[derive(Debug, Clone, Default)]
+struct WrapTable {
+    side_len: i32,
+    stitches: HashMap<FaceEdge, Stitch>
+}
+
+impl WrapTable {
+    /// Walking off one side of the map comes back in on the far side of the same row or column.
+    fn flat(grid: &Grid) -> Self {
+        let cells: HashSet<Point> = grid.face_cells().into_iter().collect();
+        let stitches = WrapTable::open_edges(grid)
+            .into_iter()
+            .map(|from| {
+                let back = from.side.reverse().to_point();
+                let mut face = from.face;
+                while cells.contains(&(face + back)) {
+                    face = face + back;
+                }
+                (from, Stitch { from, to: FaceEdge { face, side: from.side.reverse() }, reversed: false })
+            })
+            .collect();
+
+        WrapTable { side_len: grid.side_len, stitches }
+    }
+
+    /// Walking off an edge carries on over the same edge of the folded cube.
+    fn cube(grid: &Grid, cube: &Cube) -> Self {
+        let n = grid.side_len;
+        let stitches = WrapTable::open_edges(grid)
+            .into_iter()
+            .map(|from| {
+                // Where the first tile of the edge lands decides both the edge and its direction.
+                let (landed, facing) = cube.wrap(edge_tile(n, from, 0), from.side);
+                let to = FaceEdge { face: grid.face_cell(landed), side: facing.reverse() };
+                (from, Stitch { from, to, reversed: edge_index(n, to, landed) != 0 })
+            })
+            .collect();
+
+        WrapTable { side_len: n, stitches }
+    }
+
+    fn open_edges(grid: &Grid) -> Vec<FaceEdge> {
+        let cells = grid.face_cells();
+        cells
+            .iter()
+            .flat_map(|&face| [Direction::North, Direction::East, Direction::South, Direction::West].map(|side| FaceEdge { face, side }))
+            .filter(|edge| !cells.contains(&(edge.face + edge.side.to_point())))
+            .collect()
+    }
+
+    /// Checks that every stitch is matched by one leading straight back.
+    fn validate(&self) -> Result<(), String> {
+        for stitch in self.stitches.values() {
+            let back = self.stitches.get(&stitch.to).ok_or_else(|| format!("nothing leads back over {}", stitch.to))?;
+            if back.to != stitch.from || back.reversed != stitch.reversed {
+                return Err(format!("{} comes back as {}", stitch, back));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Steps off the map from `position`, which has to be on an edge without a face beyond it.
+    fn cross(&self, position: Point, facing: Direction) -> (Point, Direction) {
+        let n = self.side_len;
+        let from = FaceEdge { face: Point::new((position.x - 1) / n, (position.y - 1) / n), side: facing };
+        let stitch = self.stitches[&from];
+
+        let index = edge_index(n, from, position);
+        let index = if stitch.reversed { n - 1 - index } else { index };
+
+        (edge_tile(n, stitch.to, index), stitch.facing())
+    }
+}
+
+impl fmt::Display for WrapTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut stitches: Vec<&Stitch> = self.stitches.values().collect();
+        stitches.sort_by_key(|s| (s.from.face.y, s.from.face.x, s.from.side.direction_score()));
+
+        writeln!(f, "{} edges, faces of {} tiles:", stitches.len(), self.side_len)?;
+        for stitch in stitches {
+            writeln!(f, "  {}", stitch)?;
+        }
+        Ok(())
+    }
+}
+
+// The `index`th tile along an edge.
+fn edge_tile(side_len: i32, edge: FaceEdge, index: i32) -> Point {
+    let corner = Point::new(edge.face.x * side_len + 1, edge.face.y * side_len + 1);
+    let last = side_len - 1;
+    match edge.side {
+        Direction::North => corner + Point::new(index, 0),
+        Direction::South => corner + Point::new(index, last),
+        Direction::West => corner + Point::new(0, index),
+        Direction::East => corner + Point::new(last, index),
+    }
+}
+
+fn edge_index(side_len: i32, edge: FaceEdge, position: Point) -> i32 {
+    match edge.side {
+        Direction::North | Direction::South => position.x - 1 - edge.face.x * side_len,
+        Direction::East | Direction::West => position.y - 1 - edge.face.y * side_len,
+    }
 }
 
 #[derive(Debug)]
 struct Grid {
     tiles: HashMap<Point, Tile>,
     side_len: i32,
-    height: i32,
-    width: i32
+    wraps: WrapTable
 }
 
 impl Grid {
@@ -419,7 +516,9 @@ impl Grid {
         
             
         let side_len = (tiles.len() / 6).sqrt() as i32;
-        Grid { tiles, side_len, height: 200, width: 150 }
+        let mut grid = Grid { tiles, side_len, wraps: WrapTable::default() };
+        grid.wraps = WrapTable::flat(&grid);
+        grid
     }
 
     fn get_starting_position(&self) -> Point {
@@ -428,42 +527,47 @@ impl Grid {
         return Point { x: min_x, y: 1 }
     }
 
-    fn get_next_tile_in_direction(&self, current_pos: Point, direction: Direction) -> (Point, Tile) {
+    /// The face of the net `position` is on, in units of whole faces.
+    fn face_cell(&self, position: Point) -> Point {
+        Point::new((position.x - 1) / self.side_len, (position.y - 1) / self.side_len)
+    }
+
+    /// Every face of the net, row by row.
+    fn face_cells(&self) -> Vec<Point> {
+        let mut cells: Vec<Point> = self.tiles.keys().map(|&p| self.face_cell(p)).collect::<HashSet<Point>>().into_iter().collect();
+        cells.sort_by_key(|p| (p.y, p.x));
+        cells
+    }
 
+    /// The tile in front of `current_pos` and which way the player faces on it, going over the wrap table off the map.
+    fn get_next_tile_in_direction(&self, current_pos: Point, direction: Direction) -> (Point, Direction, Tile) {
         let next_position = current_pos + direction.to_point();
 
         if self.tiles.contains_key(&next_position) {
-            return (next_position, self.tiles[&next_position]);
+            return (next_position, direction, self.tiles[&next_position]);
         }
 
-        let edge_position = self.get_edge_position(current_pos, direction.reverse()).unwrap();
-
-        return (*edge_position, self.tiles[&edge_position]);
-    }
+        let (wrapped, facing) = self.wraps.cross(current_pos, direction);
 
-    fn get_edge_position(&self, position: Point, direction: Direction) -> Option<&Point> {
-        match direction {
-            Direction::North => {
-                self.tiles.keys().filter(|k| k.x == position.x).min_by_key(|k| k.y)
-            },
-            Direction::East => {
-                self.tiles.keys().filter(|k| k.y == position.y).max_by_key(|k| k.x)
-            },
-            Direction::South => {
-                self.tiles.keys().filter(|k| k.x == position.x).max_by_key(|k| k.y)
-            },
-            Direction::West => {
-                self.tiles.keys().filter(|k| k.y == position.y).min_by_key(|k| k.x)
-            },
-        }
+        return (wrapped, facing, self.tiles[&wrapped]);
     }
 }
+
 #[derive(Debug, Clone, Copy)]
 enum Tile {
     Floor,
     Wall
 }
-#[derive(Debug, Clone, Copy)]
+impl Palette for Tile {
+    fn color(&self) -> Rgb {
+        match self {
+            Tile::Floor => [225, 220, 200],
+            Tile::Wall => [70, 60, 80]
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum Direction {
     North = 3,
     East = 0,
@@ -481,12 +585,12 @@ impl Direction {
         }
     }
 
-    fn to_vertex(&self) -> Vertex {
+    fn arrow(&self) -> char {
         match self {
-            Direction::North => Vertex { x: 0, y: -1, z:0 },
-            Direction::East => Vertex { x: 1, y: 0, z: 0},
-            Direction::South => Vertex { x: 0, y: 1, z: 0},
-            Direction::West =>  Vertex { x: -1, y: 0, z: 0},
+            Direction::North => '^',
+            Direction::East => '>',
+            Direction::South => 'v',
+            Direction::West => '<',
         }
     }
 
@@ -532,4 +636,144 @@ enum Instruction {
 enum Rotation {
     Left,
     Right
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::point::Point;
+
+    use super::{parse_instructions, parse_map, part_one, part_two, render_trace, walk, Cube, Direction, FaceEdge, Grid, WrapTable};
+
+    const EXAMPLE: &str = "        ...#
+        .#..
+        #...
+        ....
+...#.......#
+........#...
+..#....#....
+..........#.
+        ...#....
+        .....#..
+        .#......
+        ......#.
+
+10R5L5R10L4R5L5";
+
+    // Every net of a cube, one character per face.
+    const NETS: [&str; 11] = [
+        "#...\n####\n#...",
+        "#...\n####\n.#..",
+        "#...\n####\n..#.",
+        "#...\n####\n...#",
+        ".#..\n####\n.#..",
+        ".#..\n####\n..#.",
+        "##..\n.###\n.#..",
+        "##..\n.###\n..#.",
+        "##..\n.###\n...#",
+        "##..\n.##.\n..##",
+        "###..\n..###",
+    ];
+
+    // Blows a net up into a map of open tiles with faces of `side_len` tiles.
+    fn open_map(net: &str, side_len: usize) -> String {
+        net.lines()
+            .flat_map(|line| {
+                let row: String = line.chars().map(|c| if c == '#' { ".".repeat(side_len) } else { " ".repeat(side_len) }).collect();
+                vec![row.trim_end().to_string(); side_len]
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn example_walks_flat_and_on_the_cube() {
+        assert_eq!(part_one(EXAMPLE.to_string()).to_string(), "6032");
+        assert_eq!(part_two(EXAMPLE.to_string()).to_string(), "5031");
+    }
+
+    // The answers for the puzzle input, so a change to the folding can't quietly move them.
+    #[test]
+    fn puzzle_input_answers_stay_put() {
+        let input = std::fs::read_to_string("day22_input.txt").unwrap();
+        assert_eq!(part_one(input.clone()).to_string(), "60362");
+        assert_eq!(part_two(input).to_string(), "74288");
+    }
+
+    #[test]
+    fn unknown_turns_are_a_parse_error() {
+        assert_eq!(parse_instructions("10R5L5\n").map(|(_, instructions)| instructions.len()), Ok(5));
+        assert!(parse_instructions("10R5X5").is_err());
+    }
+
+    #[test]
+    fn every_net_folds_and_wraps_back_the_way_it_came() {
+        for net in NETS {
+            let grid = Grid::parse(&open_map(net, 3));
+            let cube = Cube::fold(&grid).unwrap_or_else(|e| panic!("{}:\n{}", e, net));
+
+            let mut edges = 0;
+            for (&position, _) in grid.tiles.iter() {
+                for facing in [Direction::East, Direction::South, Direction::West, Direction::North] {
+                    if grid.tiles.contains_key(&(position + facing.to_point())) {
+                        continue;
+                    }
+
+                    let (landed, landed_facing) = cube.wrap(position, facing);
+                    assert!(grid.tiles.contains_key(&landed), "{:?} {:?} wrapped off the map:\n{}", position, facing, net);
+                    assert_eq!(cube.wrap(landed, landed_facing.reverse()), (position, facing.reverse()), "{}", net);
+                    edges += 1;
+                }
+            }
+
+            // 7 of a cube's 12 edges are cut to lay it flat, each leaving two sides of 3 tiles.
+            assert_eq!(edges, 7 * 2 * 3, "{}", net);
+            assert_eq!(WrapTable::cube(&grid, &cube).validate(), Ok(()), "{}", net);
+            assert_eq!(WrapTable::flat(&grid).validate(), Ok(()), "{}", net);
+        }
+    }
+
+    #[test]
+    fn example_edges_are_stitched_like_the_puzzle_describes() {
+        let (grid, _) = parse_map(EXAMPLE.to_string());
+        let cube = WrapTable::cube(&grid, &Cube::fold(&grid).unwrap());
+        let edge = |x, y, side| FaceEdge { face: Point::new(x, y), side };
+
+        assert_eq!(cube.stitches.len(), 14);
+        // A to C in the puzzle text: east off the middle right face comes down into the bottom right one.
+        let stitch = cube.stitches[&edge(2, 1, Direction::East)];
+        assert_eq!((stitch.to, stitch.facing(), stitch.reversed), (edge(3, 2, Direction::North), Direction::South, true));
+        assert_eq!(cube.cross(Point::new(12, 6), Direction::East), (Point::new(15, 9), Direction::South));
+
+        let flat = &grid.wraps;
+        assert_eq!(flat.stitches[&edge(2, 1, Direction::East)].to, edge(0, 1, Direction::West));
+        assert_eq!(flat.cross(Point::new(9, 1), Direction::North), (Point::new(9, 12), Direction::North));
+    }
+
+    #[test]
+    fn flat_trace_matches_the_puzzle_drawing() {
+        let (grid, instructions) = parse_map(EXAMPLE.to_string());
+        let (_, path) = walk(&grid, instructions);
+
+        assert_eq!(render_trace(&grid, &path), "        >>v#
+        .#v.
+        #.v.
+        ..v.
+...#...v..v#
+>>>v...>#.>>
+..#v...#....
+...>>>>v..#.
+        ...#....
+        .....#..
+        .#......
+        ......#.");
+    }
+
+    #[test]
+    fn folding_rejects_maps_that_are_not_cubes() {
+        let grid = Grid::parse(&open_map("######", 2));
+
+        assert!(Cube::fold(&grid).is_err());
+        assert_eq!(Cube::fold(&Grid::parse(&open_map(NETS[0], 2))).map(|c| c.side_len), Ok(2));
+        assert_eq!(Grid::parse(&open_map(NETS[0], 2)).get_starting_position(), Point::new(1, 1));
+    }
+}
@@ -1,34 +1,6 @@
-use core::panic;
-use std::{fmt::Display, collections::HashMap};
+use std::{fmt::Display, collections::{HashMap, HashSet}};
 
-use itertools::Itertools;
-
-use crate::domain::point::Point;
-
-
-static NORTH_DIRECTIONS: [Direction; 3] = [
-    Direction::NorthEast,
-    Direction::North,
-    Direction::NorthWest
-];
-
-static SOUTH_DIRECTIONS: [Direction; 3] = [
-    Direction::SouthEast,
-    Direction::South,
-    Direction::SouthWest
-];
-
-static EAST_DIRECTIONS: [Direction; 3] = [
-    Direction::NorthEast,
-    Direction::East,
-    Direction::SouthEast
-];
-
-static WEST_DIRECTIONS: [Direction; 3] = [
-    Direction::NorthWest,
-    Direction::West,
-    Direction::SouthWest
-];
+use crate::{domain::{bounds::Bounds, point::Point}, tools::options, viz::{self, GridFrame, export::{self, Animation, Image, Palette, Rgb}}};
 
 static ALL_DIRECTIONS: [Direction; 8] = [
     Direction::North,
@@ -42,271 +14,287 @@ static ALL_DIRECTIONS: [Direction; 8] = [
 ];
 
 pub fn part_one(input: String) -> impl Display {
-    let mut grid = parse(input);
-    grid.get_empty_ground_in_elf_rectangle();
-    grid = run_simulation(grid, 10);  
-    grid.get_empty_ground_in_elf_rectangle()
-}
-
-pub fn part_two(input: String) -> impl Display {
-    let grid = parse(input);
-    grid.get_empty_ground_in_elf_rectangle();
-    let rounds = run_simulation_until(grid, 1000);
-    
-    rounds
-}
+    let mut diffusion = Diffusion::new(parse(input), rules_from_options());
+    let mut snapshots = vec![diffusion.positions()];
+
+    for _ in 0..10 {
+        let result = diffusion.next().unwrap();
+        viz::emit(|| frame(&result, &diffusion.elves));
+        if export::is_enabled() {
+            snapshots.push(diffusion.positions());
+        }
+    }
 
+    if export::is_enabled() {
+        export_spread("day23_spread", &snapshots);
+    }
 
-fn run_simulation(mut grid: Grid, round_to_run: i32) -> Grid {
-    let mut round_number = 1;
-    let mut priority = vec![Direction::North, Direction::South, Direction::West, Direction::East];
+    diffusion.empty_ground()
+}
 
-    while round_number <= round_to_run {
-        // Step One, decision.
-        let grid_copy = grid.clone();
-        let elf_positions:HashMap<Point, Elf> = grid_copy.elves.into_iter().map(|e| -> (Point, Elf) {(e.position, e)}).collect();
-        for elf in grid.elves.iter_mut() {
-            let proposed = elf.decide_quick(&elf_positions, priority.clone());
-            elf.proposed_move = proposed;
+pub fn part_two(input: String) -> impl Display {
+    let mut diffusion = Diffusion::new(parse(input), rules_from_options());
+    let mut snapshots = vec![diffusion.positions()];
+
+    // Runs until the elves settle, which is the first round nobody moves.
+    let settled = loop {
+        let result = diffusion.next().unwrap();
+        viz::emit(|| frame(&result, &diffusion.elves));
+        if export::is_enabled() && (result.round.is_multiple_of(5) || result.moved == 0) {
+            snapshots.push(diffusion.positions());
         }
-
-        let grid_copy = grid.clone();
-
-        for elf in grid.elves.iter_mut() {
-           
-            if elf.proposed_move.is_none() {
-                continue;
-            }
-            if grid_copy.elves.iter().filter(|e| e.position != elf.position).any(|e|  e.proposed_move == elf.proposed_move) {
-                continue;
-            }
-            elf.move_to_spot(elf.proposed_move.unwrap());
+        if result.moved == 0 {
+            break result;
         }
+    };
 
+    if export::is_enabled() {
+        export_spread("day23_settled", &snapshots);
+    }
 
-        for elf in grid.elves.iter_mut() {
-            elf.end_of_round();
-            
-        }
-        // println!("End of Round: {}", round_number);
-        // println!("{}", grid.get_print_string());
-        round_number += 1;
-        priority.rotate_left(1);
+    settled.round
+}
 
-    }
+// `-o priority=NSWE` changes the order the elves consider moving in. `-o "rules=N:N;S:S"` replaces
+// the rules outright, each one a direction and the neighbours that must be empty to move that way,
+// and `-o crowd=N,E,S,W` sets the neighbours that make an elf want to move at all.
+fn rules_from_options() -> DiffusionRules {
+    let standard = DiffusionRules::standard();
+    let rules = match (options::get_raw("rules"), options::get_raw("priority")) {
+        (Some(rules), _) => parse_rules(&rules),
+        (None, Some(priority)) => DiffusionRules::with_priority(&priority).map(|r| r.rules),
+        (None, None) => Ok(standard.rules),
+    };
+    let crowd = match options::get_raw("crowd") {
+        Some(crowd) => parse_directions(&crowd),
+        None => Ok(standard.crowd),
+    };
+
+    return rules
+        .and_then(|rules| DiffusionRules::new(rules, crowd?))
+        .unwrap_or_else(|e| panic!("{}", e));
+}
 
-    grid
+fn parse_rules(rules: &str) -> Result<Vec<Rule>, String> {
+    return rules
+        .split(';')
+        .map(|rule| {
+            let (direction, clear) = rule
+                .split_once(':')
+                .ok_or_else(|| format!("Rules are written direction:neighbours, not {}", rule))?;
+            let direction = Direction::parse(direction)?;
+            Ok(Rule::new(direction, parse_directions(clear)?))
+        })
+        .collect();
 }
 
-fn run_simulation_until(mut grid: Grid, round_to_run: i32) -> i32 {
-    let mut round_number = 1;
-    let mut priority = vec![Direction::North, Direction::South, Direction::West, Direction::East];
+fn parse_directions(directions: &str) -> Result<Vec<Direction>, String> {
+    return directions.split(',').map(Direction::parse).collect();
+}
 
-    while round_number <= round_to_run {
+/// An elf moves towards `direction` when none of the `clear` neighbours has an elf in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    direction: Direction,
+    clear: Vec<Direction>
+}
 
-        let mut elf_moved = false;
-        let grid_copy = grid.clone();
-        let elf_positions:HashMap<Point, Elf> = grid_copy.elves.into_iter().map(|e| -> (Point, Elf) {(e.position, e)}).collect();
-        for elf in grid.elves.iter_mut() {
-            let proposed = elf.decide_quick(&elf_positions, priority.clone());
-            elf.proposed_move = proposed;
-        }
+impl Rule {
+    pub fn new(direction: Direction, clear: Vec<Direction>) -> Self {
+        return Rule { direction, clear };
+    }
 
-        let grid_copy = grid.clone();
+    // The direction itself and the two diagonals on either side of it.
+    fn facing(direction: Direction) -> Self {
+        let position = ALL_DIRECTIONS.iter().position(|&d| d == direction).unwrap();
+        let clear = [7, 0, 1].iter().map(|offset| ALL_DIRECTIONS[(position + offset) % 8]).collect();
 
-        for elf in grid.elves.iter_mut() {
-           
-            if elf.proposed_move.is_none() {
-                continue;
-            }
-            if grid_copy.elves.iter().filter(|e| e.position != elf.position).any(|e|  e.proposed_move == elf.proposed_move) {
-                continue;
-            }
+        Rule { direction, clear }
+    }
+}
 
-            elf_moved = true;
-            elf.move_to_spot(elf.proposed_move.unwrap());
-        }
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffusionRules {
+    /// Tried in order, starting one rule further along every round.
+    rules: Vec<Rule>,
+    /// Elves without anyone in these neighbours stay put.
+    crowd: Vec<Direction>
+}
 
-        for elf in grid.elves.iter_mut() {
-            elf.end_of_round();
-            
+impl DiffusionRules {
+    pub fn new(rules: Vec<Rule>, crowd: Vec<Direction>) -> Result<Self, String> {
+        if rules.is_empty() {
+            return Err("Elves need at least one direction to move in".to_string());
         }
 
-        if !elf_moved {
-            break;
-        }
-        round_number += 1;
-        priority.rotate_left(1);
+        return Ok(DiffusionRules { rules, crowd });
+    }
 
+    pub fn standard() -> Self {
+        return DiffusionRules::with_priority("NSWE").unwrap();
     }
 
-    round_number
+    /// The standard neighbourhoods, tried in the order given, such as `NSWE`.
+    pub fn with_priority(priority: &str) -> Result<Self, String> {
+        let rules = priority
+            .chars()
+            .map(|c| match c.to_ascii_uppercase() {
+                'N' => Ok(Rule::facing(Direction::North)),
+                'S' => Ok(Rule::facing(Direction::South)),
+                'W' => Ok(Rule::facing(Direction::West)),
+                'E' => Ok(Rule::facing(Direction::East)),
+                _ => Err(format!("Directions are N, S, W or E, not {}", c))
+            })
+            .collect::<Result<Vec<Rule>, String>>()?;
+
+        return DiffusionRules::new(rules, ALL_DIRECTIONS.to_vec());
+    }
 }
 
-
-fn parse(input: String) -> Grid {
-    let mut elves = Vec::new();
-    let mut y_index = 100;
-    input
-        .lines()
-        .for_each(|l| {
-            let mut x_index = 100;
-            l.chars().enumerate().for_each(|(_,c)| {
-                match c {
-                    '#' => { elves.push( 
-                        Elf { 
-                                position: Point { x: x_index, y: y_index },
-                                proposed_move: None 
-                            }); 
-                        },
-                    _ => ()
-                }
-                x_index += 1;
-            });
-            y_index += 1;
-        });
-
-    Grid { elves: elves }
+#[derive(Debug, Clone)]
+struct RoundResult {
+    /// Rounds are counted from 1.
+    round: usize,
+    moved: usize
 }
 
-#[derive(Clone, Debug)]
-struct Grid {
-    elves: Vec<Elf>
+/// The elves spreading out, one round per item. Never runs out.
+struct Diffusion {
+    elves: HashSet<Point>,
+    rules: DiffusionRules,
+    round: usize
 }
 
-impl Grid {
-
-    fn get_empty_ground_in_elf_rectangle(&self) -> i32 {
-        let (min, max) = self.get_bounds_with_elves();
+impl Diffusion {
+    fn new(elves: HashSet<Point>, rules: DiffusionRules) -> Self {
+        return Diffusion { elves, rules, round: 0 };
+    }
 
-        let x_size = max.x - min.x + 1;
-        let y_size = max.y - min.y + 1;
-        let total_area = x_size * y_size;
+    fn positions(&self) -> Vec<Point> {
+        return self.elves.iter().copied().collect();
+    }
 
-        return total_area - self.elves.len() as i32;
+    fn empty_ground(&self) -> i64 {
+        return Bounds::from_points(self.elves.iter().copied()).unwrap().area() - self.elves.len() as i64;
     }
-    fn get_bounds_with_elves(&self) -> (Point, Point) {
-        let positions = self.elves.iter().map(|p| p.position).collect_vec();
-        let min_x = positions.iter().map(|p| p.x).min().unwrap();
-        let max_x = positions.iter().map(|p| p.x).max().unwrap();
-        let min_y = positions.iter().map(|p| p.y).min().unwrap();
-        let max_y = positions.iter().map(|p| p.y).max().unwrap();
-
-        return (Point::new(min_x, min_y), Point::new(max_x, max_y));
+
+    // One bit per entry of `ALL_DIRECTIONS`, set when that neighbour has an elf in it.
+    fn neighbours(&self, elf: Point) -> u8 {
+        return ALL_DIRECTIONS
+            .iter()
+            .enumerate()
+            .filter(|(_, d)| self.elves.contains(&(elf + d.to_point())))
+            .fold(0, |mask, (i, _)| mask | 1 << i);
     }
 
-    #[allow(dead_code)]
-    fn get_print_string(&self) -> String {
-        let positions = self.elves.iter().map(|p| p.position).collect_vec();
-        let min_x = positions.iter().map(|p| p.x).min().unwrap();
-        let max_x = positions.iter().map(|p| p.x).max().unwrap();
-        let min_y = positions.iter().map(|p| p.y).min().unwrap();
-        let max_y = positions.iter().map(|p| p.y).max().unwrap();
-
-        let mut map = String::new();
-        map.push_str("\n");
-        map.push_str("   ");
-        for x in (min_x - 2)..=(max_x + 2) { 
-            map.push_str(format!("{} ", x.to_string()).as_str());
-        }
-        for y in (min_y - 2)..=(max_y + 2) {
-            map.push_str("\n");
-            map.push_str(format!("{} ", y.to_string()).as_str());
-            
-            for x in (min_x - 2)..=(max_x + 2) {
-                map.push_str("  ");
-                if positions.contains(&Point::new(x, y)) {
-                    map.push_str("#");
-                }
-                else {
-                    map.push_str(".");
-                }
-                map.push_str(" ");
-            }
+    fn propose(&self, elf: Point) -> Option<Point> {
+        let neighbours = self.neighbours(elf);
+        if neighbours & direction_mask(&self.rules.crowd) == 0 {
+            return None;
         }
 
-        return map;
+        let count = self.rules.rules.len();
+        return (0..count)
+            .map(|i| &self.rules.rules[(self.round + i) % count])
+            .find(|rule| neighbours & direction_mask(&rule.clear) == 0)
+            .map(|rule| elf + rule.direction.to_point());
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
-struct Elf {
-    position: Point,
-    proposed_move: Option<Point>
+fn direction_mask(directions: &[Direction]) -> u8 {
+    return directions
+        .iter()
+        .map(|d| ALL_DIRECTIONS.iter().position(|a| a == d).unwrap())
+        .fold(0, |mask, i| mask | 1 << i);
 }
 
-impl Elf {
-    fn decide_quick(&self, elves: &HashMap<Point, Elf>, direction_priority: Vec<Direction>) -> Option<Point> {
-
-        fn get_elves_in_directions(elves: &HashMap<Point, Elf>, origin_point: Point, directions: Vec<Direction>) -> bool {
-            for dir in directions {
-                if elves.contains_key(&(origin_point + dir.to_point())) {
-                    return true;
-                }
+impl Iterator for Diffusion {
+    type Item = RoundResult;
+
+    // Every elf proposes once and every proposal is counted once, so a round is linear in the number of elves.
+    // Custom rules can send an elf into a cell another elf is leaving, so the new positions are built
+    // separately, and a move into the cell of an elf that ends up staying put is called off.
+    fn next(&mut self) -> Option<RoundResult> {
+        let proposals: Vec<(Point, Point)> = self.elves
+            .iter()
+            .filter_map(|&elf| self.propose(elf).map(|to| (elf, to)))
+            .collect();
+
+        let mut counts: HashMap<Point, usize> = HashMap::new();
+        proposals.iter().for_each(|(_, to)| *counts.entry(*to).or_insert(0) += 1);
+
+        let mut moves: HashMap<Point, Point> = proposals.into_iter().filter(|(_, to)| counts[to] == 1).collect();
+        loop {
+            let blocked: Vec<Point> = moves
+                .iter()
+                .filter(|(_, to)| self.elves.contains(to) && !moves.contains_key(to))
+                .map(|(from, _)| *from)
+                .collect();
+            if blocked.is_empty() {
+                break;
             }
-            return false;
-        }
-        if !get_elves_in_directions(elves, self.position, ALL_DIRECTIONS.to_vec()) {
-            return None; 
+            blocked.iter().for_each(|from| { moves.remove(from); });
         }
 
+        self.elves = self.elves.iter().map(|elf| moves.get(elf).copied().unwrap_or(*elf)).collect();
+        self.round += 1;
+        return Some(RoundResult { round: self.round, moved: moves.len() });
+    }
+}
+
+fn frame(result: &RoundResult, elves: &HashSet<Point>) -> GridFrame {
+    let bounds = Bounds::from_points(elves.iter().copied()).unwrap().expand(2);
 
-        for dir in direction_priority {
-            match dir {
-                Direction::North => {
-                    if get_elves_in_directions(elves, self.position, NORTH_DIRECTIONS.to_vec()) {
-                        continue;
-                    }
-
-                    return Some(self.position + dir.to_point());
-                },
-                Direction::East => {
-                    if get_elves_in_directions(elves, self.position, EAST_DIRECTIONS.to_vec()) {
-                        continue;
-                    }
-
-                    return Some(self.position + dir.to_point());
-                },
-                Direction::South => {
-                    if get_elves_in_directions(elves, self.position, SOUTH_DIRECTIONS.to_vec()) {
-                        continue;
-                    }
-
-                    return Some(self.position + dir.to_point());
-                },
-                Direction::West => {
-                    if get_elves_in_directions(elves, self.position, WEST_DIRECTIONS.to_vec()) {
-                        continue;
-                    }
-
-                    return Some(self.position + dir.to_point());
-                },
-                Direction::NorthEast => panic!("Not a priortity!"),
-                Direction::NorthWest => panic!("Not a priortity!"),
-                Direction::SouthEast => panic!("Not a priortity!"),
-                Direction::SouthWest => panic!("Not a priortity!"),
-            }
-        }
+    GridFrame::from_bounds(format!("Round {}", result.round), bounds, true, None, |p| {
+        if elves.contains(&p) { '#' } else { '.' }
+    })
+}
+
+// Every snapshot is drawn over the area covered by all of them, so the frames line up.
+fn export_spread(name: &str, snapshots: &[Vec<Point>]) {
+    let bounds = Bounds::from_points(snapshots.iter().flatten().copied()).unwrap().expand(2);
+    let mut animation = Animation::new(80);
 
-        None
-        // 
+    for positions in snapshots {
+        let elves: HashSet<&Point> = positions.iter().collect();
+        animation.push(Image::from_bounds(bounds, true, 3, |p| {
+            if elves.contains(&p) { Tile::Elf } else { Tile::Ground }
+        }));
     }
 
-    fn move_to_spot(&mut self, new_position: Point) {
+    export::save_image(name, animation.frames().last().unwrap());
+    export::save_animation(name, &animation);
+}
 
+fn parse(input: String) -> HashSet<Point> {
+    return input
+        .lines()
+        .enumerate()
+        .flat_map(|(y, line)| {
+            line.chars()
+                .enumerate()
+                .filter(|(_, c)| *c == '#')
+                .map(move |(x, _)| Point::new(x as i32, y as i32))
+        })
+        .collect();
+}
 
-        //println!("Elf {} to {}", self.position, new_position);
-        self.position = new_position;
-    }
+enum Tile {
+    Elf,
+    Ground
+}
 
-    fn end_of_round(&mut self) {
-        self.proposed_move = None;
+impl Palette for Tile {
+    fn color(&self) -> Rgb {
+        match self {
+            Tile::Elf => [90, 200, 110],
+            Tile::Ground => [60, 45, 35]
+        }
     }
 }
 
-
-#[derive(Debug, Clone, Copy)]
-enum Direction {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
     North,
     East,
     South,
@@ -314,11 +302,25 @@ enum Direction {
     NorthEast,
     NorthWest,
     SouthEast,
-    SouthWest  
+    SouthWest
 }
 
 impl Direction {
-    fn to_point(&self) -> Point {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name.trim().to_ascii_uppercase().as_str() {
+            "N" => Ok(Direction::North),
+            "NE" => Ok(Direction::NorthEast),
+            "E" => Ok(Direction::East),
+            "SE" => Ok(Direction::SouthEast),
+            "S" => Ok(Direction::South),
+            "SW" => Ok(Direction::SouthWest),
+            "W" => Ok(Direction::West),
+            "NW" => Ok(Direction::NorthWest),
+            other => Err(format!("Directions are N, NE, E, SE, S, SW, W or NW, not {}", other))
+        }
+    }
+
+    fn to_point(self) -> Point {
         match self {
             Direction::North => Point { x: 0, y: -1 },
             Direction::East => Point { x: 1, y: 0},
@@ -330,4 +332,89 @@ impl Direction {
             Direction::SouthWest => Point { x: -1, y: 1}
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::domain::point::Point;
+
+    use super::{parse, parse_directions, parse_rules, Diffusion, DiffusionRules, Direction};
+
+    const EXAMPLE: &str = "....#..
+..###.#
+#...#.#
+.#...##
+#.###..
+##.#.##
+.#..#..";
+
+    #[test]
+    fn small_example_spreads_out_and_settles() {
+        let small = ".....\n..##.\n..#..\n.....\n..##.\n.....";
+        let mut diffusion = Diffusion::new(parse(small.to_string()), DiffusionRules::standard());
+
+        let moved = diffusion.by_ref().take(4).map(|r| r.moved).collect::<Vec<_>>();
+
+        assert_eq!(moved, vec![3, 5, 3, 0]);
+        assert_eq!(diffusion.empty_ground(), 30 - 5);
+    }
+
+    #[test]
+    fn larger_example_matches_both_parts() {
+        let mut diffusion = Diffusion::new(parse(EXAMPLE.to_string()), DiffusionRules::standard());
+        diffusion.by_ref().take(10).for_each(drop);
+        assert_eq!(diffusion.empty_ground(), 110);
+
+        let settled = diffusion.find(|r| r.moved == 0).unwrap();
+        assert_eq!(settled.round, 20);
+    }
+
+    #[test]
+    fn priority_is_configurable() {
+        let rules = DiffusionRules::with_priority("ew").unwrap();
+
+        assert_eq!(rules.rules.iter().map(|r| r.direction).collect::<Vec<_>>(), vec![Direction::East, Direction::West]);
+        assert_eq!(rules.rules[0].clear, vec![Direction::NorthEast, Direction::East, Direction::SouthEast]);
+        assert!(DiffusionRules::with_priority("NX").is_err());
+    }
+
+    #[test]
+    fn neighbourhoods_are_configurable() {
+        // The second elf is north-east of the first, so the first can't go north by the standard rules.
+        let elves = HashSet::from([Point::new(0, 0), Point::new(1, -1)]);
+        let mut standard = Diffusion::new(elves.clone(), DiffusionRules::standard());
+        standard.next();
+        assert_eq!(standard.elves, HashSet::from([Point::new(0, 1), Point::new(1, -2)]));
+
+        // Only the square straight ahead has to be empty.
+        let ahead = DiffusionRules::new(parse_rules("N:N;S:S;W:W;E:E").unwrap(), DiffusionRules::standard().crowd).unwrap();
+        let mut straight = Diffusion::new(elves.clone(), ahead);
+        straight.next();
+        assert_eq!(straight.elves, HashSet::from([Point::new(0, -1), Point::new(1, -2)]));
+
+        // Diagonal neighbours don't count as a crowd, so nobody moves.
+        let orthogonal = DiffusionRules::new(DiffusionRules::standard().rules, parse_directions("N,E,S,W").unwrap()).unwrap();
+        let mut calm = Diffusion::new(elves.clone(), orthogonal);
+        assert_eq!(calm.next().unwrap().moved, 0);
+        assert_eq!(calm.elves, elves);
+
+        assert!(parse_rules("N").is_err());
+    }
+
+    #[test]
+    fn elves_can_follow_each_other_into_vacated_cells() {
+        // Only the north-east has to be clear, so an elf can step into the cell of the elf in front.
+        let rules = DiffusionRules::new(parse_rules("N:NE").unwrap(), DiffusionRules::standard().crowd).unwrap();
+        let mut queue = Diffusion::new(HashSet::from([Point::new(0, 0), Point::new(0, -1)]), rules.clone());
+        assert_eq!(queue.next().unwrap().moved, 2);
+        assert_eq!(queue.elves, HashSet::from([Point::new(0, -1), Point::new(0, -2)]));
+
+        // The elf in front can't go because of the elf to its north-east, so the one behind stays too.
+        let mut blocked = Diffusion::new(HashSet::from([Point::new(0, 0), Point::new(0, -1), Point::new(1, -2)]), rules);
+        assert_eq!(blocked.next().unwrap().moved, 1);
+        assert_eq!(blocked.elves, HashSet::from([Point::new(0, 0), Point::new(0, -1), Point::new(1, -3)]));
+        assert!(DiffusionRules::new(vec![], vec![Direction::North]).is_err());
+    }
+}
@@ -1,429 +1,359 @@
-use std::{fmt::Display, collections::{HashSet, HashMap}, time::Instant};
+use std::{fmt::Display, collections::{HashSet, HashMap}};
 
-use itertools::Itertools;
-use priority_queue::PriorityQueue;
-
-use crate::domain::point::Point;
+use crate::{domain::{bounds::Bounds, point::Point}, tools::{cycle::Cycle, options, verbosity}, viz::{self, GridFrame}};
 
 pub fn part_one(input: String) -> impl Display {
-    let grid_state = GridState::parse(input);
-
-    // println!("{:?} - {:?}", grid_state.start, grid_state.end);
-    // println!("{}", grid_state.print_grid());
-    // let grid_state = grid_state.add_minutes(1);
-    // println!("{}", grid_state.print_grid());
-    // let grid_state = grid_state.add_minutes(1);
-    // println!("{}", grid_state.print_grid());
-    let grid_states = get_grid_states(&grid_state, 1000);
-    // let result = find_path_djistrkas(grid_state.start, grid_state.end, &grid_state, &Vec::new(), Player { position: grid_state.start }, &grid_states, 0);
-    // println!("{:?}", result);
-
-    // for x in grid_states.values() {
-    //     println!("{}", x.print_grid());
-    // }
-
-    let res = find_path_a_star(grid_state.start, grid_state.end, &grid_state, &grid_states, 0);
-    res
+    let basin = Basin::parse(&input);
+    trip_minutes(&basin, &basin.laps(1))
 }
 
 pub fn part_two(input: String) -> impl Display {
-    let grid_state = GridState::parse(input);
-    let grid_states = get_grid_states(&grid_state, 1000);    
-    let trip_1 = find_path_a_star(grid_state.start, grid_state.end, &grid_state, &grid_states, 0);
-    let trip_2 = find_path_a_star(grid_state.end, grid_state.start, &grid_state, &grid_states, trip_1);
-    let trip_3 = find_path_a_star(grid_state.start, grid_state.end, &grid_state, &grid_states, trip_2);
-    
-    trip_3
+    let basin = Basin::parse(&input);
+    trip_minutes(&basin, &planned_waypoints(&basin))
 }
 
+// Part two crosses the basin three times. `-o laps=N` asks for another number of crossings, and
+// `-o "waypoints=start;3,2;end"` replaces the plan with any list of stops. Part one is always a single crossing.
+fn planned_waypoints(basin: &Basin) -> Vec<Point> {
+    return match options::get_raw("waypoints") {
+        Some(list) => list
+            .split(';')
+            .map(|w| basin.waypoint(w))
+            .collect::<Result<Vec<Point>, String>>()
+            .unwrap_or_else(|e| panic!("{}", e)),
+        None => basin.laps(options::get_or("laps", 3)),
+    };
+}
 
-fn get_grid_states(grid_state: &GridState, minutes_to_map: i32) -> HashMap<i32, GridState> {
+fn trip_minutes(basin: &Basin, waypoints: &[Point]) -> i32 {
+    let trip = basin.trip(waypoints, 0).unwrap_or_else(|e| panic!("{}", e));
+    trip.show(basin);
 
-    let mut grid_states = HashMap::new();
-    grid_states.insert(0, grid_state.clone());
-    let mut previous_state = grid_state.clone();
-    for x in 1..=minutes_to_map {
-        let new_state = previous_state.add_minutes(1);
-        grid_states.insert(x, new_state.clone());
-        previous_state = new_state;
-    }
+    return trip.arrival() - trip.departure;
+}
 
-    grid_states
+/// The valley with the blizzards as they are at minute 0. The walls are at `x = 0`, `y = 0`,
+/// `x = width + 1` and `y = height + 1`, apart from the gaps for the start and the end.
+struct Basin {
+    width: i32,
+    height: i32,
+    start: Point,
+    end: Point,
+    /// Where each kind of blizzard starts, indexed `[y - 1][x - 1]`.
+    east: Vec<Vec<bool>>,
+    west: Vec<Vec<bool>>,
+    north: Vec<Vec<bool>>,
+    south: Vec<Vec<bool>>,
+    /// Every blizzard is back where it started after lcm(width, height) minutes, right from minute 0.
+    blizzard_cycle: Cycle
 }
 
+impl Basin {
+    fn parse(input: &str) -> Self {
+        let lines: Vec<&str> = input.lines().filter(|l| !l.trim().is_empty()).collect();
+        let width = lines[0].len() as i32 - 2;
+        let height = lines.len() as i32 - 2;
+
+        let gap = |line: &str, y: i32| Point::new(line.find('.').expect("No gap in the wall") as i32, y);
+        let start = gap(lines[0], 0);
+        let end = gap(lines[lines.len() - 1], height + 1);
+
+        let blizzards = |kind: char| {
+            lines[1..=height as usize]
+                .iter()
+                .map(|line| line.chars().skip(1).take(width as usize).map(|c| c == kind).collect())
+                .collect()
+        };
+
+        return Basin {
+            width,
+            height,
+            start,
+            end,
+            east: blizzards('>'),
+            west: blizzards('<'),
+            north: blizzards('^'),
+            south: blizzards('v'),
+            blizzard_cycle: Cycle { start: 0, length: num::integer::lcm(width, height) as usize }
+        };
+    }
+
+    // A blizzard moving east is at `x` at `minute` if it started `minute` columns further west, and so on.
+    fn has_blizzard(&self, p: Point, minute: i32) -> bool {
+        let (x, y) = (p.x - 1, p.y - 1);
+        let (w, h) = (self.width, self.height);
+
+        return self.east[y as usize][(x - minute).rem_euclid(w) as usize]
+            || self.west[y as usize][(x + minute).rem_euclid(w) as usize]
+            || self.south[(y - minute).rem_euclid(h) as usize][x as usize]
+            || self.north[(y + minute).rem_euclid(h) as usize][x as usize];
+    }
 
-fn find_path_a_star(start_point: Point, end_point: Point, grid_state: &GridState, grid_states: &HashMap<i32, GridState>, start_minute: i32) -> i32 {
-    let mut frontier:PriorityQueue<(Point, i32), i32> = PriorityQueue::new();
-    let mut closed:Vec<(Point, i32)> = Vec::new();
-    let possible_moves = vec![Direction::North.to_point(), Direction::East.to_point(), Direction::West.to_point(), Direction:: South.to_point(), Point::new(0, 0)];
-    let mut path: HashMap<(Point, i32), (Point, i32)> = HashMap::new();
-    let mut cost_map: HashMap<(Point, i32), i32> = HashMap::new();
-    let mut timed = i32::MAX;
-    frontier.push((start_point, start_minute), 0);
-    
-    while frontier.len() > 0 { 
-        //let timer = Instant::now();
-        let popped = frontier.pop().unwrap();
-        let (current_pos, current_minutes) = popped.0;
-        // let current_minutes = popped.0.1;
-        closed.push((current_pos, current_minutes));
-        if current_pos == end_point {
-            timed = current_minutes;
-            break;
+    fn is_open(&self, p: Point, minute: i32) -> bool {
+        if p == self.start || p == self.end {
+            return true;
+        }
+        if p.x < 1 || p.x > self.width || p.y < 1 || p.y > self.height {
+            return false;
         }
 
-        // if current_minutes % 10 == 0 {
-        //     println!("{} {}", current_pos, current_minutes);
-        // }
-        let next_grid_state = &grid_states[&(current_minutes + 1)];
-    
-        for possible in possible_moves.to_vec() { 
-            let next_pos = current_pos + possible;
-            if next_pos.x < 0 || next_pos.x > next_grid_state.size.x || next_pos.y < 0 || next_pos.y > next_grid_state.size.y {
-                //println!("Out of bounds");
-                continue;
-            }
-            if next_grid_state.walls.contains(&next_pos) {
-                //println!("Wall");
-                continue;
-            }
-            if next_grid_state.blizzard_positions.contains(&next_pos) {
-                //println!("Blizzard");
-                continue;
-            }
-    
-            let cost = *cost_map.get(&(current_pos, current_minutes)).unwrap_or(&0) + 1;
-            let next_minutes = current_minutes + 1;
-            let next_key = &(next_pos, next_minutes);
-            if frontier.get(&(next_pos, next_minutes)).is_some() && cost < *cost_map.get(next_key).unwrap_or(&0) {
-                frontier.remove(&(next_pos, next_minutes));
-            }
+        return !self.has_blizzard(p, minute);
+    }
 
-            if closed.contains(next_key) && cost < *cost_map.get(next_key).unwrap_or(&0) {
-                closed.retain(|&x| x != *next_key);
+    /// The quickest way from `from` to `to` leaving at `start_minute`, or `None` if there isn't one.
+    /// Every position reachable at a minute is expanded together, and a position at the same point
+    /// in the blizzard cycle as one seen before is dropped, so the search ends even without a way through.
+    fn cross(&self, from: Point, to: Point, start_minute: i32) -> Option<Crossing> {
+        let moves = [Point::new(0, 0), Point::new(1, 0), Point::new(0, 1), Point::new(-1, 0), Point::new(0, -1)];
+        let phase = |minute: i32| self.blizzard_cycle.normalize(minute as u64);
+        let mut seen: HashSet<(Point, usize)> = HashSet::from([(from, phase(start_minute))]);
+        let mut frontier = vec![from];
+        // For every minute, where each reachable position was reached from.
+        let mut came_from: Vec<HashMap<Point, Point>> = Vec::new();
+        let mut minute = start_minute;
+
+        while !frontier.contains(&to) {
+            if frontier.is_empty() {
+                return None;
             }
 
-            if !frontier.get(next_key).is_some() && !closed.contains(next_key) {
-
-                // let value = path.entry(next_pos).or_insert(current_pos);
-                path.insert(*next_key, (current_pos, current_minutes));
-                cost_map.insert(*next_key, cost);
-                
-                let priority = cost + heuristic(next_pos, end_point);
-                frontier.push(*next_key, -priority);
+            minute += 1;
+            let mut reached = HashMap::new();
+            for &p in frontier.iter() {
+                for next in moves.iter().map(|&m| p + m) {
+                    if self.is_open(next, minute) && seen.insert((next, phase(minute))) {
+                        reached.insert(next, p);
+                    }
+                }
             }
+
+            frontier = reached.keys().copied().collect();
+            came_from.push(reached);
         }
 
+        let mut route = vec![to];
+        for step in came_from.iter().rev() {
+            route.push(step[route.last().unwrap()]);
+        }
+        route.reverse();
 
-        //let time = timer.elapsed();
-        //println!("{:.2?}", time);
-        // let current_copy;
-        // {
-        //     let current = &mut grid.get_node(current_pos);
-        //     current_copy = current.clone();
-        // }
+        return Some(Crossing { departure: start_minute, arrival: minute, route });
     }
 
-    println!("Path Length: {}", path.len());
-
-    println!("TIME! {}", timed);
-
-    timed
-    // println!("Start: {}", path[&start_point]);
-    // for x in path.iter() {
-    //     println!("{} -> {}", x.0, x.1);
-    // }
-    // //println!("path length: {:?}", path);
-}
-
-fn heuristic(a:Point, b:Point) -> i32 {
-    return (a.x - b.x).abs() + (a.y - b.y).abs();
-}
-
+    /// Visits `waypoints` in order, leaving the first one at `departure` and setting off for the
+    /// next stop as soon as the previous one is reached. A single waypoint is a trip of no minutes.
+    fn trip(&self, waypoints: &[Point], departure: i32) -> Result<Trip, String> {
+        let Some(&start) = waypoints.first() else {
+            return Err("A trip needs at least one waypoint to start from".to_string());
+        };
+
+        let mut legs: Vec<Crossing> = Vec::new();
+        for pair in waypoints.windows(2) {
+            let minute = legs.last().map_or(departure, |leg| leg.arrival);
+            let leg = self.cross(pair[0], pair[1], minute)
+                .ok_or_else(|| format!("No way from {} to {} leaving at minute {}", pair[0], pair[1], minute))?;
+            legs.push(leg);
+        }
 
-fn find_path_djistrkas(start_point: Point, end_point: Point, grid_state: &GridState, path: &Vec<Point>, player: Player, grid_states: &HashMap<i32, GridState>, minutes: i32) -> PathFit {
-    let mut paths: Vec<PathFit> = Vec::new();
-    let possible_moves = vec![Direction::North.to_point(), Direction::East.to_point(), Direction::West.to_point(), Direction:: South.to_point(), Point::new(0, 0)];
+        return Ok(Trip { departure, start, legs });
+    }
 
-    let next_grid_state = &grid_states[&minutes];
-    let blizzard_positions = next_grid_state.blizzards.iter().map(|b| b.position).collect_vec();
+    // Back and forth between the start and the end, `laps` crossings in all.
+    fn laps(&self, laps: usize) -> Vec<Point> {
+        return (0..=laps).map(|i| if i % 2 == 0 { self.start } else { self.end }).collect();
+    }
 
-    
-    for possible in possible_moves {
-        if minutes > 25 {
-            continue;
-        }
-        else if minutes < 5 {
-        println!("{}", minutes);
-        }
-        let next_pos = player.position + possible;
+    /// `start`, `end`, or an `x,y` position that isn't a wall.
+    fn waypoint(&self, name: &str) -> Result<Point, String> {
+        let point = match name.trim() {
+            "start" => self.start,
+            "end" => self.end,
+            coords => {
+                let parsed = coords
+                    .split_once(',')
+                    .and_then(|(x, y)| Some(Point::new(x.trim().parse().ok()?, y.trim().parse().ok()?)));
+                parsed.ok_or_else(|| format!("Waypoints are start, end or x,y, not `{}`", coords))?
+            }
+        };
 
-        if next_pos.x < 0 || next_pos.x >= next_grid_state.size.x || next_pos.y < 0 || next_pos.y >= next_grid_state.size.y {
-            continue;
+        let inside = (1..=self.width).contains(&point.x) && (1..=self.height).contains(&point.y);
+        if !inside && point != self.start && point != self.end {
+            return Err(format!("Waypoint {} is in the wall", point));
         }
-        if next_grid_state.walls.contains(&next_pos) {
-            continue;
-        }
-        if blizzard_positions.contains(&next_pos) {
-            continue;
-        }
-
-        let elapsed = minutes + 1;
-        let mut branch = path.clone();
-        branch.push(next_pos);
 
-        let full_path = find_path_djistrkas(start_point, end_point, &next_grid_state, path, Player { position: next_pos }, grid_states, elapsed);
+        return Ok(point);
+    }
 
-        let mut add_path = path.clone();
-        add_path.extend(full_path.path);
+    fn frame(&self, expedition: Point, minute: i32, caption: String) -> GridFrame {
+        let bounds = Bounds::new(Point::new(0, 0), Point::new(self.width + 1, self.height + 1));
+        let (w, h) = (self.width, self.height);
 
-        if add_path.contains(&end_point) {
-            paths.push(PathFit { path: add_path, minutes: full_path.minutes + 1 });
-            break;
-        }
-    }
+        return GridFrame::from_bounds(caption, bounds, true, Some(expedition), |p| {
+            if p == expedition {
+                return 'E';
+            }
+            if p == self.start || p == self.end {
+                return '.';
+            }
+            if !(1..=w).contains(&p.x) || !(1..=h).contains(&p.y) {
+                return '#';
+            }
 
-    let mut best_path = PathFit { path: Vec::new(), minutes: i32::MAX };
-    for path_fit in paths {
-        if path_fit.minutes < best_path.minutes {
-            best_path = path_fit;
-        }
+            let (x, y) = (p.x - 1, p.y - 1);
+            let blizzards = [
+                (self.north[(y + minute).rem_euclid(h) as usize][x as usize], '^'),
+                (self.east[y as usize][(x - minute).rem_euclid(w) as usize], '>'),
+                (self.south[(y - minute).rem_euclid(h) as usize][x as usize], 'v'),
+                (self.west[y as usize][(x + minute).rem_euclid(w) as usize], '<'),
+            ];
+            let here: Vec<char> = blizzards.iter().filter(|(present, _)| *present).map(|(_, c)| *c).collect();
+            return match here.as_slice() {
+                [] => '.',
+                [single] => *single,
+                many => char::from_digit(many.len() as u32, 10).unwrap()
+            };
+        });
     }
-    best_path
-    
 }
 
-#[derive(Debug, Clone, Copy)]
-struct Player {
-    position: Point
+/// One trip across the basin. `route[i]` is where the expedition is at minute `departure + i`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Crossing {
+    departure: i32,
+    arrival: i32,
+    route: Vec<Point>
 }
 
-#[derive(Debug, Clone)]
-struct PathFit {
-    path: Vec<Point>,
-    minutes: i32
+impl Crossing {
+    fn minutes(&self) -> i32 {
+        return self.arrival - self.departure;
+    }
 }
 
-// fn path_find(start_point: Point, end_point: Point, grid: &mut Grid) -> i32 {
-//     let mut frontier:PriorityQueue<Point, i32> = PriorityQueue::new();
-//     let mut closed:Vec<Point> = Vec::new();
-
-//     let start = grid.get_node(start_point);
-//     frontier.push(start.position, 0);
-
-//     while frontier.len() > 0 { 
-
-//         let popped = frontier.pop().unwrap();
-
-//         let current_pos = popped.0;
-//         let current_copy;
-//         {
-//             let current = &mut grid.get_node(current_pos);
-//             current_copy = current.clone();
-//         }
-
-//         closed.push(current_copy.position);
-
-//         if current_copy.position == end_point {
-//             break;
-//         }
-
-        
-//         let neighbors = grid.get_neighbors(current_copy.position);
+/// Crossings between consecutive waypoints, each leaving when the one before it arrives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Trip {
+    departure: i32,
+    start: Point,
+    legs: Vec<Crossing>
+}
 
-//         let neighbors_count = neighbors.len();
-//         for x in 0..neighbors_count {
+impl Trip {
+    fn arrival(&self) -> i32 {
+        return self.legs.last().map_or(self.departure, |leg| leg.arrival);
+    }
 
-//             let neighbor_copy;
-//             {
-//                 let neighbor = &mut grid.get_node(neighbors[x]);
-//                 neighbor_copy = neighbor.clone();
-//             }
+    fn leg_minutes(&self) -> Vec<i32> {
+        return self.legs.iter().map(|leg| leg.minutes()).collect();
+    }
 
-//             if (current_copy.height - neighbor_copy.height) < -1 {
-//                 continue;
-//             }
+    /// Minutes since the departure at the end of each leg.
+    fn cumulative_minutes(&self) -> Vec<i32> {
+        return self.legs.iter().map(|leg| leg.arrival - self.departure).collect();
+    }
 
-//             let cost = current_copy.cost + 1;
+    /// Where the expedition is at every minute from the departure to the arrival.
+    fn route(&self) -> Vec<Point> {
+        let mut route = vec![self.start];
+        self.legs.iter().for_each(|leg| route.extend_from_slice(&leg.route[1..]));
 
-//             if frontier.get(&neighbor_copy.position).is_some() && cost < neighbor_copy.cost {
-            
-//                 frontier.remove(&neighbor_copy.position);
-//             }
+        return route;
+    }
 
-//             if closed.contains(&neighbor_copy.position) && cost < neighbor_copy.cost {
-//                 closed.retain(|&x| x != neighbor_copy.position);
-//             }
+    fn show(&self, basin: &Basin) {
+        if verbosity::is_debug() {
+            let minutes = self.leg_minutes().into_iter().zip(self.cumulative_minutes());
+            for (leg, (minutes, total)) in self.legs.iter().zip(minutes) {
+                println!("{} -> {}: {} minutes, {} in total", leg.route[0], leg.route[leg.route.len() - 1], minutes, total);
+            }
+        }
 
-//             if !frontier.get(&neighbor_copy.position).is_some() && !closed.contains(&neighbor_copy.position) {
+        for (minute, position) in (self.departure..).zip(self.route()) {
+            viz::emit(|| basin.frame(position, minute, format!("Minute {}", minute)));
+        }
+    }
+}
 
-//                 grid.update_node(cost, current_copy.position, neighbor_copy.position);
-//                 grid.height_map.get_mut(&current_copy.position).unwrap().cost = cost;
+#[cfg(test)]
+mod tests {
+    use crate::{domain::point::Point, tools::cycle::Cycle};
+
+    use super::Basin;
+
+    const EXAMPLE: &str = "#.######
+#>>.<^<#
+#.<..<<#
+#>v.><>#
+#<^v^^>#
+######.#";
+
+    #[test]
+    fn blizzards_are_placed_analytically() {
+        let basin = Basin::parse(EXAMPLE);
+
+        assert_eq!((basin.width, basin.height), (6, 4));
+        assert_eq!(basin.blizzard_cycle, Cycle { start: 0, length: 12 });
+        assert!(basin.has_blizzard(Point::new(1, 1), 0));
+        // The `>` starting at (1, 1) has moved on, and the `<` from (4, 1) hasn't arrived yet.
+        assert!(!basin.has_blizzard(Point::new(1, 1), 1));
+        assert!(basin.has_blizzard(Point::new(3, 1), 1));
+        assert!(basin.is_open(basin.start, 5) && !basin.is_open(Point::new(0, 1), 5));
+    }
 
-//                 let priority = cost + heuristic(neighbor_copy.position, end_point);
-//                 frontier.push(neighbor_copy.position, -priority);
-//             }
-//         }
+    #[test]
+    fn crossings_match_the_example() {
+        let basin = Basin::parse(EXAMPLE);
 
-//     }
+        let there = basin.cross(basin.start, basin.end, 0).unwrap();
+        let back = basin.cross(basin.end, basin.start, there.arrival).unwrap();
+        let again = basin.cross(basin.start, basin.end, back.arrival).unwrap();
 
-//     let mut current_goal = grid.get_node(end_point);
+        assert_eq!((there.arrival, back.arrival, again.arrival), (18, 41, 54));
+        assert_eq!(there.route.len(), 19);
+        assert_eq!((there.route[0], there.route[18]), (basin.start, basin.end));
+        assert!(there.route.iter().enumerate().all(|(minute, &p)| basin.is_open(p, minute as i32)));
+    }
 
-//     let mut counter = 0;
-//     while current_goal.prev.is_some() && counter < 2000 {
-//         current_goal = grid.get_node(current_goal.prev.unwrap());
-//         counter = counter + 1;
-//     }
-//     return counter;
-// }
+    #[test]
+    fn trips_report_legs_and_route() {
+        let basin = Basin::parse(EXAMPLE);
 
-#[derive(Debug, Clone)]
-struct GridState {
-    minutes: i32,
-    blizzards: Vec<Blizzard>,
-    walls: HashSet<Point>,
-    blizzard_positions: Vec<Point>,
-    size: Point,
-    start: Point,
-    end: Point
-}
+        let trip = basin.trip(&basin.laps(3), 0).unwrap();
+        assert_eq!(trip.leg_minutes(), vec![18, 23, 13]);
+        assert_eq!(trip.cumulative_minutes(), vec![18, 41, 54]);
+        assert_eq!(trip.route().len(), 55);
+        assert!(trip.route().iter().enumerate().all(|(minute, &p)| basin.is_open(p, minute as i32)));
 
-impl GridState {
-    fn add_minutes(&self, minutes_to_add: i32) -> Self {
-        let mut next_state = self.clone();
-
-        // move blizzards
-        let mut count = next_state.minutes;
-        let target = next_state.minutes + minutes_to_add;
-        
-        while count < target {
-            for blizzard in next_state.blizzards.iter_mut() {
-                let mut next_pos = blizzard.position + blizzard.direction.to_point();
-                if self.walls.contains(&next_pos) {
-                    if next_pos.x == self.size.x {
-                        next_pos = Point::new(1, next_pos.y);
-                    }
-                    else if next_pos.x == 0 {
-                        next_pos = Point::new(self.size.x - 1, next_pos.y);
-                    }
-                    else if next_pos.y == self.size.y {
-                        next_pos = Point::new(next_pos.x, 1);
-                    }
-                    else if next_pos.y == 0 {
-                        next_pos = Point::new(next_pos.x, self.size.y - 1);
-                    }
-                }
-
-                blizzard.position = next_pos;
-                
-            }
-
-            count += 1;
-        }
+        let via = ["start", "3,2", "end"].map(|w| basin.waypoint(w).unwrap());
+        let detour = basin.trip(&via, 0).unwrap();
+        assert!(detour.route().contains(&Point::new(3, 2)));
+        assert!(detour.arrival() >= 18);
+    }
 
-        next_state.blizzard_positions = next_state.blizzards.iter().map(|b| b.position).collect_vec();
-        next_state.minutes = target;
+    #[test]
+    fn staying_put_takes_no_time() {
+        let basin = Basin::parse(EXAMPLE);
 
-        return next_state;
+        let trip = basin.trip(&basin.laps(0), 5).unwrap();
+        assert_eq!(trip.arrival(), 5);
+        assert!(trip.leg_minutes().is_empty());
+        assert_eq!(trip.route(), vec![basin.start]);
     }
-    fn print_grid(&self) -> String {
-
-        let mut map = String::new();
-        map.push_str("\n");
-        map.push_str("   ");
-
-        for y in 0..=self.size.y {
-            map.push_str("\n");
-            
-            for x in 0..=self.size.x {
-                if self.walls.contains(&Point::new(x, y)) {
-                    map.push_str("#");
-                    continue;
-                }
 
-                let blizzards = self.blizzards.iter().filter(|b| b.position == Point::new(x, y)).collect_vec();
-                if blizzards.len() == 1 {
-                    match blizzards[0].direction {
-                        Direction::North => map.push_str("^"),
-                        Direction::East => map.push_str(">"),
-                        Direction::South => map.push_str("v"),
-                        Direction::West => map.push_str("<"),
-                    }
-                }
-                else if blizzards.len() > 1 {
-                    map.push_str(blizzards.len().to_string().as_str());
-                }
-                else {
-                    map.push_str(".")
-                }
-            }
-        }
+    #[test]
+    fn bad_waypoints_are_rejected() {
+        let basin = Basin::parse(EXAMPLE);
 
-        return map;
-    }
-    fn parse(input: String) -> Self {
-        let mut blizzards = Vec::new();
-        let mut walls = HashSet::new();
-
-        let line_total = input.lines().count();
-        let mut y_index = 0;
-        let mut start:Option<Point> = None;
-        let mut end:Option<Point> = None;
-        input
-            .lines()
-            .enumerate()
-            .for_each(|(n, l)| {
-                let mut x_index = 0;
-                l.chars().enumerate().for_each(|(_,c)| {
-                    match c {
-                        '#' => { 
-                            walls.insert(Point::new(x_index, y_index));
-                            },
-                        '>' => blizzards.push(Blizzard { direction: Direction::East, position: Point::new(x_index, y_index)}),
-                        '<' => blizzards.push(Blizzard { direction: Direction::West, position: Point::new(x_index, y_index)}),
-                        '^' => blizzards.push(Blizzard { direction: Direction::North, position: Point::new(x_index, y_index)}),
-                        'v' => blizzards.push(Blizzard { direction: Direction::South, position: Point::new(x_index, y_index)}),
-                        _ => ()
-                    }
-                    if n == 0 && c == '.' {
-                        start = Some(Point::new(x_index, y_index));
-                    }
-                    if n == line_total - 1 && c == '.' {
-                        end = Some(Point::new(x_index, y_index));   
-                    }
-                    x_index += 1;
-                });
-                y_index += 1;
-            });
-
-        let max_x = walls.iter().map(|point| point.x).max().unwrap();
-        let max_y = walls.iter().map(|point| point.y).max().unwrap();
-    
-        GridState { minutes: 0, blizzards: blizzards, walls: walls, size: Point::new(max_x,max_y), start: start.unwrap(), end: end.unwrap(), blizzard_positions: Vec::new() }
+        assert!(basin.waypoint("0,1").is_err());
+        assert!(basin.waypoint("middle").is_err());
+        assert!(basin.trip(&[], 0).is_err());
     }
-}
 
-#[derive(Debug, Clone)]
-struct Blizzard {
-    position: Point,
-    direction: Direction
-}
+    #[test]
+    fn walled_in_goal_has_no_crossing() {
+        let basin = Basin::parse(EXAMPLE);
 
-#[derive(Debug, Clone, Copy)]
-enum Direction {
-    North = 3,
-    East = 0,
-    South = 1,
-    West = 2
-}
-
-impl Direction {
-    fn to_point(&self) -> Point {
-        match self {
-            Direction::North => Point { x: 0, y: -1 },
-            Direction::East => Point { x: 1, y: 0},
-            Direction::South => Point { x: 0, y: 1 },
-            Direction::West =>  Point { x: -1, y: 0},
-        }
+        assert_eq!(basin.cross(basin.start, Point::new(0, 2), 0), None);
+        assert_eq!(basin.cross(basin.start, Point::new(100, 100), 0), None);
     }
 }
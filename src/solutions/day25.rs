@@ -1,40 +1,45 @@
-use std::{fmt::Display, collections::HashMap};
+use std::fmt::Display;
+
+use crate::domain::snafu::Snafu;
 
 pub fn part_one(input: String) -> impl Display {
-    let mut sum: i64 = 0;
-    input.lines().for_each(|line| {
-        sum += snafu_to_dec(line);
-    });
-    let snafu = dec_to_snafu(sum);
-    snafu
+    fuel_requirements(&input)
 }
 
-pub fn part_two(input: String) -> impl Display {
+pub fn part_two(_input: String) -> impl Display {
     "I win"
 }
 
-
-fn snafu_to_dec(snafu: &str) -> i64 {
-    let d: HashMap<char, i64> =
-        HashMap::from([('=', -2), ('-', -1), ('0', 0), ('1', 1), ('2', 2)]);
-    let mut n: i64 = 0;
-    for (i, ch) in snafu.char_indices().rev() {
-        let place: i64 = (snafu.len() - i - 1) as i64;
-        n += d[&ch] * 5_i64.pow(place as u32);
-    }
-    n
+// Added up in SNAFU directly, so the total never has to fit in an integer.
+fn fuel_requirements(input: &str) -> Snafu {
+    input
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.parse::<Snafu>().unwrap_or_else(|e| panic!("{}: {}", line, e)))
+        .sum()
 }
 
-fn dec_to_snafu(dec: i64) -> String {
-    let mut snafu = String::new();
-    let mut n = dec;
-    let s: HashMap<i64, char> =
-        HashMap::from([(3, '='), (4, '-'), (0, '0'), (1, '1'), (2, '2')]);
-    while n != 0 {
-        let rem: i64 = n % 5;
-        let digit = s[&rem];
-        snafu.push(digit);
-        n = (n + 2) / 5;
+#[cfg(test)]
+mod tests {
+    use super::fuel_requirements;
+
+    const EXAMPLE: &str = "1=-0-2
+12111
+2=0=
+21
+2=01
+111
+20012
+112
+1=-1=
+1-12
+12
+1=
+122";
+
+    #[test]
+    fn fuel_adds_up_like_the_example() {
+        assert_eq!(fuel_requirements(EXAMPLE).to_string(), "2=-1=0");
+        assert_eq!(fuel_requirements(EXAMPLE).to_i64(), Some(4890));
     }
-    snafu.chars().rev().collect::<String>()
 }
@@ -0,0 +1,180 @@
+use std::{collections::HashMap, hash::Hash};
+
+/// A repeating run of states. Step `start` is the first state inside the loop and
+/// step `start + length` is the first time it comes around again.
+///
+/// Steps are counted from the initial state: step 0 is the initial state and
+/// step `n` is the state after `n` transitions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cycle {
+    pub start: usize,
+    pub length: usize
+}
+
+impl Cycle {
+    /// The earliest step that is in the same state as `step`.
+    pub fn normalize(&self, step: u64) -> usize {
+        let start = self.start as u64;
+        if step < start {
+            return step as usize;
+        }
+
+        return (start + (step - start) % self.length as u64) as usize;
+    }
+
+    /// The accumulated value after `steps` transitions, where `deltas[i]` is the change made
+    /// going from step `i` to step `i + 1`. Needs at least `start + length` deltas.
+    pub fn extrapolate(&self, deltas: &[i64], steps: u64) -> i64 {
+        assert!(deltas.len() >= self.start + self.length, "Not enough deltas to cover the cycle");
+
+        if steps <= self.start as u64 {
+            return deltas[..steps as usize].iter().sum();
+        }
+
+        let lead_in: i64 = deltas[..self.start].iter().sum();
+        let looped = &deltas[self.start..self.start + self.length];
+        let per_loop: i64 = looped.iter().sum();
+
+        let remaining = steps - self.start as u64;
+        let full_loops = (remaining / self.length as u64) as i64;
+        let leftover: i64 = looped[..(remaining % self.length as u64) as usize].iter().sum();
+
+        return lead_in + full_loops * per_loop + leftover;
+    }
+}
+
+/// Floyd's tortoise and hare. Keeps two states in memory.
+pub fn floyd<T: Clone + PartialEq, F: Fn(&T) -> T>(initial: T, step: F) -> Cycle {
+    let mut tortoise = step(&initial);
+    let mut hare = step(&tortoise);
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&step(&hare));
+    }
+
+    let mut start = 0;
+    tortoise = initial;
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        start += 1;
+    }
+
+    let mut length = 1;
+    hare = step(&tortoise);
+    while tortoise != hare {
+        hare = step(&hare);
+        length += 1;
+    }
+
+    return Cycle { start, length };
+}
+
+/// Brent's algorithm. Same result as `floyd` with fewer calls to `step`.
+pub fn brent<T: Clone + PartialEq, F: Fn(&T) -> T>(initial: T, step: F) -> Cycle {
+    let mut power = 1;
+    let mut length = 1;
+    let mut tortoise = initial.clone();
+    let mut hare = step(&initial);
+    while tortoise != hare {
+        if power == length {
+            tortoise = hare.clone();
+            power *= 2;
+            length = 0;
+        }
+        hare = step(&hare);
+        length += 1;
+    }
+
+    tortoise = initial.clone();
+    hare = initial;
+    for _ in 0..length {
+        hare = step(&hare);
+    }
+
+    let mut start = 0;
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        start += 1;
+    }
+
+    return Cycle { start, length };
+}
+
+/// Finds the first key that shows up twice. The key only needs to capture whatever
+/// decides the future of the simulation, so it can be much smaller than the full state.
+pub fn find_repeat<K: Hash + Eq, I: IntoIterator<Item = K>>(keys: I) -> Option<Cycle> {
+    let mut detector = RepeatDetector::new();
+    return keys.into_iter().find_map(|key| detector.push(key));
+}
+
+/// Incremental form of `find_repeat`, for simulations that need to stop as soon as a cycle shows up.
+pub struct RepeatDetector<K> {
+    seen: HashMap<K, usize>,
+    step: usize
+}
+
+impl<K: Hash + Eq> RepeatDetector<K> {
+    pub fn new() -> Self {
+        return RepeatDetector { seen: HashMap::new(), step: 0 };
+    }
+
+    /// Records the key for the next step, returning the cycle once the key has been seen before.
+    pub fn push(&mut self, key: K) -> Option<Cycle> {
+        let step = self.step;
+        self.step += 1;
+
+        if let Some(&start) = self.seen.get(&key) {
+            return Some(Cycle { start, length: step - start });
+        }
+
+        self.seen.insert(key, step);
+        return None;
+    }
+}
+
+impl<K: Hash + Eq> Default for RepeatDetector<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{brent, find_repeat, floyd, Cycle};
+
+    // 0 -> 1 -> 2 -> 3 -> 4 -> 5 -> 2 ...
+    fn step(x: &i32) -> i32 {
+        if *x == 5 { 2 } else { x + 1 }
+    }
+
+    #[test]
+    fn floyd_and_brent_agree() {
+        assert_eq!(floyd(0, step), Cycle { start: 2, length: 4 });
+        assert_eq!(brent(0, step), Cycle { start: 2, length: 4 });
+        assert_eq!(brent(3, step), Cycle { start: 0, length: 4 });
+    }
+
+    #[test]
+    fn find_repeat_matches_on_keys() {
+        let states = [(0, 'a'), (1, 'b'), (2, 'c'), (3, 'b'), (4, 'c')];
+
+        let cycle = find_repeat(states.iter().map(|(_, key)| *key));
+
+        assert_eq!(cycle, Some(Cycle { start: 1, length: 2 }));
+        assert_eq!(find_repeat(vec![1, 2, 3]), None);
+    }
+
+    #[test]
+    fn cycle_extrapolates_deltas() {
+        let cycle = Cycle { start: 2, length: 3 };
+        let deltas = vec![10, 20, 1, 2, 3];
+
+        assert_eq!(cycle.extrapolate(&deltas, 2), 30);
+        assert_eq!(cycle.extrapolate(&deltas, 5), 36);
+        assert_eq!(cycle.extrapolate(&deltas, 9), 30 + 6 + 6 + 1);
+        assert_eq!(cycle.extrapolate(&deltas, 2 + 3 * 1_000_000_000), 30 + 6 * 1_000_000_000);
+        assert_eq!(cycle.normalize(9), 3);
+    }
+}
@@ -0,0 +1,150 @@
+use std::{collections::{HashMap, VecDeque}, fmt, hash::Hash};
+
+/// Cache for recursive solvers, keyed by whatever state fully determines the answer.
+///
+/// Recursive functions take a `&mut Memo` and check it on the way in:
+///
+/// ```ignore
+/// if let Some(best) = memo.get(&state) {
+///     return best;
+/// }
+/// let best = /* recurse */;
+/// memo.insert(state, best)
+/// ```
+///
+/// A bounded memo evicts its oldest entries first once it is full.
+pub struct Memo<K, V> {
+    cache: HashMap<K, V>,
+    order: VecDeque<K>,
+    capacity: Option<usize>,
+    hits: u64,
+    misses: u64
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Memo<K, V> {
+    pub fn new() -> Self {
+        return Memo { cache: HashMap::new(), order: VecDeque::new(), capacity: None, hits: 0, misses: 0 };
+    }
+
+    /// A memo that holds at most `capacity` entries.
+    pub fn bounded(capacity: usize) -> Self {
+        assert!(capacity > 0, "A bounded memo needs room for at least one entry");
+        return Memo { capacity: Some(capacity), ..Memo::new() };
+    }
+
+    pub fn get(&mut self, key: &K) -> Option<V> {
+        match self.cache.get(key) {
+            Some(value) => {
+                self.hits += 1;
+                Some(value.clone())
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Stores the value and hands it back, so it can be the last expression of the solver.
+    pub fn insert(&mut self, key: K, value: V) -> V {
+        if let Some(capacity) = self.capacity {
+            if !self.cache.contains_key(&key) {
+                while self.cache.len() >= capacity {
+                    let oldest = self.order.pop_front().unwrap();
+                    self.cache.remove(&oldest);
+                }
+                self.order.push_back(key.clone());
+            }
+        }
+
+        self.cache.insert(key, value.clone());
+        return value;
+    }
+
+    /// Looks the key up, computing and storing the value on a miss.
+    /// The closure cannot use the memo itself; recursive solvers should use `get` and `insert`.
+    pub fn get_or_insert_with<F: FnOnce() -> V>(&mut self, key: K, compute: F) -> V {
+        if let Some(value) = self.get(&key) {
+            return value;
+        }
+
+        let value = compute();
+        return self.insert(key, value);
+    }
+
+    pub fn len(&self) -> usize {
+        return self.cache.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.cache.is_empty();
+    }
+
+    pub fn clear(&mut self) {
+        self.cache.clear();
+        self.order.clear();
+    }
+
+    pub fn stats(&self) -> MemoStats {
+        return MemoStats { hits: self.hits, misses: self.misses, entries: self.cache.len() };
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Default for Memo<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for MemoStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let lookups = self.hits + self.misses;
+        let rate = if lookups == 0 { 0.0 } else { self.hits as f64 * 100.0 / lookups as f64 };
+        write!(f, "{} hits, {} misses ({:.1}% hit rate), {} entries", self.hits, self.misses, rate, self.entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Memo, MemoStats};
+
+    fn fibonacci(n: u64, memo: &mut Memo<u64, u64>) -> u64 {
+        if n < 2 {
+            return n;
+        }
+        if let Some(value) = memo.get(&n) {
+            return value;
+        }
+
+        let value = fibonacci(n - 1, memo) + fibonacci(n - 2, memo);
+        memo.insert(n, value)
+    }
+
+    #[test]
+    fn memo_caches_recursive_results() {
+        let mut memo = Memo::new();
+
+        assert_eq!(fibonacci(90, &mut memo), 2880067194370816120);
+        assert_eq!(memo.stats(), MemoStats { hits: 87, misses: 89, entries: 89 });
+    }
+
+    #[test]
+    fn bounded_memo_evicts_oldest_entries() {
+        let mut memo = Memo::bounded(2);
+        memo.insert(1, "one");
+        memo.insert(2, "two");
+        memo.insert(3, "three");
+
+        assert_eq!(memo.len(), 2);
+        assert_eq!(memo.get(&1), None);
+        assert_eq!(memo.get_or_insert_with(3, || "unused"), "three");
+        assert_eq!(memo.stats().hits, 1);
+    }
+}
@@ -0,0 +1,94 @@
+//! Reads the 4x6 block letters that puzzles draw on a screen, such as the day 10 CRT.
+//! Letters are 4 pixels wide with one blank column between them.
+
+pub const LETTER_WIDTH: usize = 4;
+pub const LETTER_HEIGHT: usize = 6;
+const LETTER_SPACING: usize = LETTER_WIDTH + 1;
+
+static FONT: [(char, [&str; LETTER_HEIGHT]); 17] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+/// Decodes rows of lit pixels into letters. Glyphs that aren't in the font come back as `?`.
+pub fn read_letters(pixels: &[Vec<bool>]) -> String {
+    if pixels.len() < LETTER_HEIGHT {
+        return String::new();
+    }
+
+    let width = pixels.iter().take(LETTER_HEIGHT).map(|row| row.len()).min().unwrap_or(0);
+    let letter_count = (width + 1) / LETTER_SPACING;
+
+    (0..letter_count)
+        .map(|index| read_letter(pixels, index * LETTER_SPACING))
+        .collect()
+}
+
+/// Same as `read_letters`, for a screen drawn with `#` for lit pixels.
+pub fn read_text(render: &str) -> String {
+    let pixels: Vec<Vec<bool>> = render
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| line.chars().map(|c| c == '#').collect())
+        .collect();
+
+    read_letters(&pixels)
+}
+
+fn read_letter(pixels: &[Vec<bool>], left: usize) -> char {
+    FONT.iter()
+        .find(|(_, glyph)| {
+            glyph.iter().enumerate().all(|(y, row)| {
+                row.chars().enumerate().all(|(x, c)| pixels[y][left + x] == (c == '#'))
+            })
+        })
+        .map(|(letter, _)| *letter)
+        .unwrap_or('?')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::read_text;
+
+    #[test]
+    fn ocr_reads_a_full_crt_screen() {
+        let screen = "
+            ####.###....##.###..###..#..#..##..#..#.
+            #....#..#....#.#..#.#..#.#.#..#..#.#..#.
+            ###..#..#....#.###..#..#.##...#..#.####.
+            #....###.....#.#..#.###..#.#..####.#..#.
+            #....#....#..#.#..#.#.#..#.#..#..#.#..#.
+            ####.#.....##..###..#..#.#..#.#..#.#..#.";
+
+        assert_eq!(read_text(screen), "EPJBRKAH");
+    }
+
+    #[test]
+    fn ocr_marks_unknown_glyphs() {
+        let screen = "
+            #..#.####
+            #..#.#..#
+            ####.#..#
+            #..#.#..#
+            #..#.#..#
+            #..#.####";
+
+        assert_eq!(read_text(screen), "H?");
+    }
+}
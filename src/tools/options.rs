@@ -0,0 +1,37 @@
+use std::{collections::HashMap, str::FromStr, sync::Mutex};
+
+/// Puzzle-specific settings, set once by the runner from `-o key=value` flags,
+/// such as `-o agents=3` for day 16. Each day documents the keys it reads.
+static OPTIONS: Mutex<Option<HashMap<String, String>>> = Mutex::new(None);
+
+pub fn set(key: &str, value: &str) {
+    OPTIONS.lock().unwrap().get_or_insert_with(HashMap::new).insert(key.to_string(), value.to_string());
+}
+
+pub fn get_raw(key: &str) -> Option<String> {
+    OPTIONS.lock().unwrap().as_ref()?.get(key).cloned()
+}
+
+/// The option parsed as `T`. A value that doesn't parse is reported and ignored.
+pub fn get<T: FromStr>(key: &str) -> Option<T> {
+    let raw = get_raw(key)?;
+    match raw.parse() {
+        Ok(value) => Some(value),
+        Err(_) => {
+            println!("Ignoring option {}={}: not a valid value", key, raw);
+            None
+        }
+    }
+}
+
+pub fn get_or<T: FromStr>(key: &str, default: T) -> T {
+    get(key).unwrap_or(default)
+}
+
+/// Splits a `key=value` flag.
+pub fn parse_pair(pair: &str) -> Result<(String, String), String> {
+    match pair.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => Ok((key.trim().to_string(), value.trim().to_string())),
+        _ => Err(format!("expected key=value, got `{}`", pair))
+    }
+}
@@ -0,0 +1,154 @@
+use std::str::FromStr;
+
+use nom::{
+    IResult,
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::{char, digit1, multispace0, space1},
+    combinator::{map_res, opt, recognize, verify},
+    multi::separated_list1,
+    sequence::{delimited, pair, preceded, tuple},
+    error::ParseError,
+};
+use num::{Signed, Unsigned};
+
+/// A number with an optional leading `-` and, for types that accept it, a fractional part.
+pub fn signed<T: FromStr + Signed>(input: &str) -> IResult<&str, T> {
+    map_res(
+        recognize(tuple((opt(char('-')), digit1, opt(pair(char('.'), digit1))))),
+        T::from_str
+    )(input)
+}
+
+pub fn unsigned<T: FromStr + Unsigned>(input: &str) -> IResult<&str, T> {
+    map_res(digit1, T::from_str)(input)
+}
+
+/// Wraps a parser so it ignores surrounding whitespace.
+pub fn ws<'a, O, E: ParseError<&'a str>, F>(inner: F) -> impl FnMut(&'a str) -> IResult<&'a str, O, E>
+where
+    F: FnMut(&'a str) -> IResult<&'a str, O, E>,
+{
+    delimited(multispace0, inner, multispace0)
+}
+
+/// A literal that may be surrounded by whitespace.
+pub fn token<'a>(literal: &'static str) -> impl FnMut(&'a str) -> IResult<&'a str, &'a str> {
+    ws(tag(literal))
+}
+
+/// One or more items separated by commas, e.g. `79, 98` or `AA,BB`.
+pub fn comma_separated<'a, O, F>(item: F) -> impl FnMut(&'a str) -> IResult<&'a str, Vec<O>>
+where
+    F: FnMut(&'a str) -> IResult<&'a str, O>,
+{
+    separated_list1(ws(char(',')), item)
+}
+
+/// One or more items separated by spaces or tabs.
+pub fn space_separated<'a, O, F>(item: F) -> impl FnMut(&'a str) -> IResult<&'a str, Vec<O>>
+where
+    F: FnMut(&'a str) -> IResult<&'a str, O>,
+{
+    separated_list1(space1, item)
+}
+
+/// Consumes the whole input and returns every integer in it, skipping whatever text sits between them.
+/// A `-` directly before the digits is kept when `T` can represent it.
+pub fn all_integers<T: FromStr>(input: &str) -> IResult<&str, Vec<T>> {
+    let mut numbers = Vec::new();
+    let mut rest = input;
+
+    while !rest.is_empty() {
+        let number: IResult<&str, T> = alt((
+            map_res(recognize(preceded(char('-'), digit1)), T::from_str),
+            map_res(digit1, T::from_str)
+        ))(rest);
+
+        match number {
+            Ok((remaining, value)) => {
+                numbers.push(value);
+                rest = remaining;
+            }
+            Err(_) => {
+                let skip = rest.chars().next().map(|c| c.len_utf8()).unwrap_or(0);
+                rest = &rest[skip..];
+            }
+        }
+    }
+
+    Ok((rest, numbers))
+}
+
+/// Like `all_integers`, but fails unless exactly `count` integers are found.
+pub fn n_integers<T: FromStr>(count: usize) -> impl FnMut(&str) -> IResult<&str, Vec<T>> {
+    move |input| verify(all_integers::<T>, |numbers: &Vec<T>| numbers.len() == count)(input)
+}
+
+/// Splits the input into the groups of lines separated by blank lines.
+/// Lines keep their leading whitespace; surrounding line breaks are dropped.
+pub fn blocks(input: &str) -> Vec<&str> {
+    let mut blocks = Vec::new();
+    let mut block: Option<(usize, usize)> = None;
+    let mut offset = 0;
+
+    for line in input.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\r', '\n']);
+        if content.trim().is_empty() {
+            if let Some((start, end)) = block.take() {
+                blocks.push(&input[start..end]);
+            }
+        } else {
+            let start = block.map(|(start, _)| start).unwrap_or(offset);
+            block = Some((start, offset + content.len()));
+        }
+        offset += line.len();
+    }
+
+    if let Some((start, end)) = block {
+        blocks.push(&input[start..end]);
+    }
+
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use nom::{character::complete::alpha1, sequence::preceded};
+
+    use super::{all_integers, blocks, comma_separated, signed, token, unsigned, n_integers};
+
+    #[test]
+    fn signed_parses_negative_numbers_and_decimals() {
+        assert_eq!(signed::<i32>("-12 rest"), Ok((" rest", -12)));
+        assert_eq!(signed::<i64>("42"), Ok(("", 42)));
+        assert_eq!(signed::<f64>("-1.5,"), Ok((",", -1.5)));
+        assert_eq!(signed::<i32>("4. Each"), Ok((". Each", 4)));
+        assert!(unsigned::<u64>("-3").is_err());
+    }
+
+    #[test]
+    fn tokens_and_lists_ignore_whitespace() {
+        let result = preceded(token("Starting items:"), comma_separated(unsigned::<u64>))("  Starting items: 79, 98,54");
+        assert_eq!(result, Ok(("", vec![79, 98, 54])));
+
+        let valves = comma_separated(alpha1)("DD, II, BB");
+        assert_eq!(valves, Ok(("", vec!["DD", "II", "BB"])));
+    }
+
+    #[test]
+    fn all_integers_grabs_every_number_on_the_line() {
+        let line = "Sensor at x=2, y=-18: closest beacon is at x=-2, y=15";
+
+        assert_eq!(all_integers::<i32>(line).unwrap().1, vec![2, -18, -2, 15]);
+        assert_eq!(all_integers::<u32>("move 2 from -5 to 9").unwrap().1, vec![2, 5, 9]);
+        assert!(n_integers::<i32>(3)("1 2").is_err());
+    }
+
+    #[test]
+    fn blocks_split_on_blank_lines() {
+        let input = "    [D]\n[N] [C]\n 1   2 \n\nmove 1 from 2 to 1\r\n\r\n\r\nlast\n";
+
+        assert_eq!(blocks(input), vec!["    [D]\n[N] [C]\n 1   2 ", "move 1 from 2 to 1", "last"]);
+    }
+}
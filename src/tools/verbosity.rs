@@ -0,0 +1,21 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// How much extra output solutions print, set once by the runner from `-v` flags.
+/// 0 is answers only, 1 adds debug output such as rendered grids, 2 adds step-by-step traces.
+static LEVEL: AtomicU8 = AtomicU8::new(0);
+
+pub fn set_level(level: u8) {
+    LEVEL.store(level, Ordering::Relaxed);
+}
+
+pub fn level() -> u8 {
+    LEVEL.load(Ordering::Relaxed)
+}
+
+pub fn is_debug() -> bool {
+    level() >= 1
+}
+
+pub fn is_trace() -> bool {
+    level() >= 2
+}
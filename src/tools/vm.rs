@@ -0,0 +1,386 @@
+//! A small cycle-counting machine for assembly-style puzzles.
+//!
+//! An `InstructionSet` names each instruction with its cycle cost and its effect on the
+//! registers. A `Machine` runs a parsed program one cycle at a time and calls a `Hook`
+//! during every cycle, before the instruction in flight has taken effect. That is the
+//! moment the day 10 CPU samples its register.
+
+use std::{collections::{BTreeMap, HashMap, HashSet}, error, fmt};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operand {
+    Register(String),
+    Immediate(i64)
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operand::Register(name) => write!(f, "{}", name),
+            Operand::Immediate(value) => write!(f, "{}", value)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    pub name: String,
+    pub operands: Vec<Operand>
+}
+
+impl Instruction {
+    /// Reads `name operand operand...`. Operands that parse as integers are immediates,
+    /// anything else names a register.
+    pub fn parse(line: &str) -> Option<Instruction> {
+        let mut tokens = line.split_whitespace();
+        let name = tokens.next()?.to_string();
+        let operands = tokens
+            .map(|token| match token.parse::<i64>() {
+                Ok(value) => Operand::Immediate(value),
+                Err(_) => Operand::Register(token.to_string())
+            })
+            .collect();
+
+        return Some(Instruction { name, operands });
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        self.operands.iter().try_for_each(|operand| write!(f, " {}", operand))
+    }
+}
+
+/// Named registers. Registers that were never written read as 0.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Registers {
+    values: BTreeMap<String, i64>
+}
+
+impl Registers {
+    pub fn new() -> Self {
+        return Registers::default();
+    }
+
+    pub fn with(initial: &[(&str, i64)]) -> Self {
+        return Registers { values: initial.iter().map(|(name, value)| (name.to_string(), *value)).collect() };
+    }
+
+    pub fn get(&self, name: &str) -> i64 {
+        return self.values.get(name).copied().unwrap_or(0);
+    }
+
+    pub fn set(&mut self, name: &str, value: i64) {
+        self.values.insert(name.to_string(), value);
+    }
+
+    /// The register's value, or the number itself for an immediate.
+    pub fn value(&self, operand: &Operand) -> i64 {
+        match operand {
+            Operand::Register(name) => self.get(name),
+            Operand::Immediate(value) => *value
+        }
+    }
+}
+
+impl fmt::Display for Registers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let values: Vec<String> = self.values.iter().map(|(name, value)| format!("{}={}", name, value)).collect();
+        write!(f, "{}", values.join(" "))
+    }
+}
+
+/// Where execution goes once an instruction has taken effect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+    Next,
+    /// Moves the program counter by this many instructions, relative to the current one.
+    Jump(i64),
+    Halt
+}
+
+pub type Effect = fn(&mut Registers, &[Operand]) -> Flow;
+
+#[derive(Clone, Copy)]
+pub struct InstructionDefinition {
+    pub cycles: u32,
+    pub arity: usize,
+    pub effect: Effect
+}
+
+#[derive(Clone, Default)]
+pub struct InstructionSet {
+    definitions: HashMap<String, InstructionDefinition>
+}
+
+impl InstructionSet {
+    pub fn new() -> Self {
+        return InstructionSet::default();
+    }
+
+    /// Adds an instruction that takes `arity` operands and applies `effect` at the end of its last cycle.
+    pub fn define(mut self, name: &str, cycles: u32, arity: usize, effect: Effect) -> Self {
+        assert!(cycles > 0, "Instruction {} has to take at least one cycle", name);
+        self.definitions.insert(name.to_string(), InstructionDefinition { cycles, arity, effect });
+        return self;
+    }
+
+    pub fn get(&self, name: &str) -> Option<&InstructionDefinition> {
+        return self.definitions.get(name);
+    }
+
+    /// Parses one instruction per non-empty line, checking each against its definition.
+    pub fn parse_program(&self, source: &str) -> Result<Vec<Instruction>, VmError> {
+        return source
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                let instruction = Instruction::parse(line).unwrap();
+                let definition = self.get(&instruction.name)
+                    .ok_or_else(|| VmError::UnknownInstruction { line: index + 1, name: instruction.name.clone() })?;
+                if definition.arity != instruction.operands.len() {
+                    return Err(VmError::WrongArity {
+                        line: index + 1,
+                        name: instruction.name,
+                        expected: definition.arity,
+                        found: instruction.operands.len()
+                    });
+                }
+
+                Ok(instruction)
+            })
+            .collect();
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VmError {
+    UnknownInstruction { line: usize, name: String },
+    WrongArity { line: usize, name: String, expected: usize, found: usize }
+}
+
+impl fmt::Display for VmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VmError::UnknownInstruction { line, name } => write!(f, "line {}: unknown instruction {}", line, name),
+            VmError::WrongArity { line, name, expected, found } =>
+                write!(f, "line {}: {} takes {} operands, found {}", line, name, expected, found)
+        }
+    }
+}
+
+impl error::Error for VmError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    Continue,
+    Stop
+}
+
+/// Watches the machine. `on_cycle` runs during every cycle, numbered from 1.
+pub trait Hook {
+    fn on_cycle(&mut self, cycle: u64, registers: &Registers) -> Control;
+}
+
+impl<F: FnMut(u64, &Registers) -> Control> Hook for F {
+    fn on_cycle(&mut self, cycle: u64, registers: &Registers) -> Control {
+        self(cycle, registers)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Breakpoint {
+    /// Stops before this cycle starts.
+    Cycle(u64),
+    /// Stops before the instruction at this address starts.
+    Address(usize)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    /// The program ran past its last instruction or an instruction halted it.
+    Halted,
+    Breakpoint(Breakpoint),
+    /// The hook asked to stop after the cycle it was called for.
+    Hook
+}
+
+pub struct Machine<'a> {
+    instruction_set: &'a InstructionSet,
+    program: Vec<Instruction>,
+    registers: Registers,
+    pc: usize,
+    cycle: u64,
+    // Cycles left on the instruction at `pc`, once it has started.
+    in_flight: Option<u32>,
+    breakpoints: HashSet<Breakpoint>,
+    resuming: bool,
+    trace: bool
+}
+
+impl<'a> Machine<'a> {
+    pub fn new(instruction_set: &'a InstructionSet, program: Vec<Instruction>, registers: Registers) -> Self {
+        return Machine {
+            instruction_set,
+            program,
+            registers,
+            pc: 0,
+            cycle: 0,
+            in_flight: None,
+            breakpoints: HashSet::new(),
+            resuming: false,
+            trace: false
+        };
+    }
+
+    pub fn registers(&self) -> &Registers {
+        return &self.registers;
+    }
+
+    pub fn pc(&self) -> usize {
+        return self.pc;
+    }
+
+    /// The number of cycles completed so far.
+    pub fn cycle(&self) -> u64 {
+        return self.cycle;
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.breakpoints.insert(breakpoint);
+    }
+
+    pub fn remove_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.breakpoints.remove(&breakpoint);
+    }
+
+    /// Prints every cycle with the instruction in flight and the registers going into it.
+    pub fn set_trace(&mut self, trace: bool) {
+        self.trace = trace;
+    }
+
+    /// Runs until the program halts, a breakpoint is hit or the hook stops it.
+    /// Calling `run` again after a breakpoint carries on from where it stopped.
+    pub fn run<H: Hook>(&mut self, hook: &mut H) -> Stop {
+        loop {
+            if let Some(stop) = self.step(hook) {
+                return stop;
+            }
+        }
+    }
+
+    /// Runs a single cycle, unless the machine is halted or sitting on a breakpoint.
+    pub fn step<H: Hook>(&mut self, hook: &mut H) -> Option<Stop> {
+        if self.pc >= self.program.len() {
+            return Some(Stop::Halted);
+        }
+
+        let next_cycle = self.cycle + 1;
+        if !self.resuming {
+            let hit = [Breakpoint::Cycle(next_cycle), Breakpoint::Address(self.pc)]
+                .into_iter()
+                .filter(|b| self.in_flight.is_none() || matches!(b, Breakpoint::Cycle(_)))
+                .find(|b| self.breakpoints.contains(b));
+            if let Some(breakpoint) = hit {
+                self.resuming = true;
+                return Some(Stop::Breakpoint(breakpoint));
+            }
+        }
+        self.resuming = false;
+
+        let instruction = &self.program[self.pc];
+        let definition = self.instruction_set.get(&instruction.name).expect("Program was parsed with this instruction set");
+        let remaining = self.in_flight.unwrap_or(definition.cycles);
+
+        self.cycle = next_cycle;
+        if self.trace {
+            println!(
+                "{:>6} | {:>4}: {:<12} ({}/{}) | {}",
+                self.cycle, self.pc, instruction.to_string(), definition.cycles - remaining + 1, definition.cycles, self.registers
+            );
+        }
+        let control = hook.on_cycle(self.cycle, &self.registers);
+
+        if remaining > 1 {
+            self.in_flight = Some(remaining - 1);
+        } else {
+            self.in_flight = None;
+            match (definition.effect)(&mut self.registers, &instruction.operands) {
+                Flow::Next => self.pc += 1,
+                Flow::Jump(offset) => self.pc = usize::try_from(self.pc as i64 + offset).unwrap_or(usize::MAX),
+                Flow::Halt => self.pc = self.program.len()
+            }
+        }
+
+        return match control {
+            Control::Continue => None,
+            Control::Stop => Some(Stop::Hook)
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Breakpoint, Control, Flow, InstructionSet, Machine, Registers, Stop, VmError};
+
+    fn assembunny() -> InstructionSet {
+        InstructionSet::new()
+            .define("cpy", 1, 2, |r, o| {
+                if let super::Operand::Register(name) = &o[1] {
+                    r.set(name, r.value(&o[0]));
+                }
+                Flow::Next
+            })
+            .define("inc", 1, 1, |r, o| { r.set(&o[0].to_string(), r.value(&o[0]) + 1); Flow::Next })
+            .define("dec", 1, 1, |r, o| { r.set(&o[0].to_string(), r.value(&o[0]) - 1); Flow::Next })
+            .define("jnz", 3, 2, |r, o| if r.value(&o[0]) != 0 { Flow::Jump(r.value(&o[1])) } else { Flow::Next })
+    }
+
+    #[test]
+    fn effects_land_after_the_last_cycle_of_an_instruction() {
+        let cpu = InstructionSet::new()
+            .define("noop", 1, 0, |_, _| Flow::Next)
+            .define("addx", 2, 1, |r, o| { r.set("X", r.get("X") + r.value(&o[0])); Flow::Next });
+        let program = cpu.parse_program("noop\naddx 3\naddx -5").unwrap();
+        let mut machine = Machine::new(&cpu, program, Registers::with(&[("X", 1)]));
+
+        let mut seen = Vec::new();
+        let stop = machine.run(&mut |_, r: &Registers| { seen.push(r.get("X")); Control::Continue });
+
+        assert_eq!(stop, Stop::Halted);
+        assert_eq!(seen, vec![1, 1, 1, 4, 4]);
+        assert_eq!(machine.registers().get("X"), -1);
+    }
+
+    #[test]
+    fn jumps_loop_and_breakpoints_pause_the_run() {
+        let set = assembunny();
+        let program = set.parse_program("cpy 3 a\ninc b\ndec a\njnz a -2").unwrap();
+        let mut machine = Machine::new(&set, program, Registers::new());
+        let mut ignore = |_, _: &Registers| Control::Continue;
+
+        machine.add_breakpoint(Breakpoint::Address(3));
+        assert_eq!(machine.run(&mut ignore), Stop::Breakpoint(Breakpoint::Address(3)));
+        assert_eq!((machine.cycle(), machine.registers().get("b")), (3, 1));
+
+        machine.remove_breakpoint(Breakpoint::Address(3));
+        machine.add_breakpoint(Breakpoint::Cycle(10));
+        assert_eq!(machine.run(&mut ignore), Stop::Breakpoint(Breakpoint::Cycle(10)));
+        assert_eq!(machine.run(&mut ignore), Stop::Halted);
+        assert_eq!(machine.registers().get("b"), 3);
+        assert_eq!(machine.cycle(), 1 + 3 * 2 + 3 * 3);
+    }
+
+    #[test]
+    fn programs_are_checked_against_the_instruction_set() {
+        let set = assembunny();
+
+        assert_eq!(set.parse_program("inc a\nmul a b"), Err(VmError::UnknownInstruction { line: 2, name: "mul".to_string() }));
+        assert_eq!(
+            set.parse_program("cpy a"),
+            Err(VmError::WrongArity { line: 1, name: "cpy".to_string(), expected: 2, found: 1 })
+        );
+    }
+}
@@ -0,0 +1,119 @@
+//! Frames emitted by the simulation days, and the recorder that collects them when the
+//! runner is started with `--visualize`. The frames are played back by `viz::player`.
+
+use std::sync::{atomic::{AtomicBool, Ordering}, Mutex};
+
+use crate::domain::{bounds::Bounds, point::Point};
+
+pub mod export;
+pub mod player;
+
+/// One picture of a simulation, already drawn as text.
+pub trait Frame: Send {
+    fn caption(&self) -> String;
+    fn lines(&self) -> &[String];
+
+    /// The `(column, row)` the viewer should keep on screen, such as the falling grain of sand.
+    fn focus(&self) -> Option<(usize, usize)> {
+        None
+    }
+}
+
+pub struct GridFrame {
+    caption: String,
+    lines: Vec<String>,
+    focus: Option<(usize, usize)>
+}
+
+impl GridFrame {
+    pub fn new(caption: impl Into<String>, lines: Vec<String>, focus: Option<(usize, usize)>) -> Self {
+        GridFrame { caption: caption.into(), lines, focus }
+    }
+
+    /// Draws every point in `bounds` with `tile`. Rows run top to bottom in increasing `y`
+    /// when `y_down` is set, otherwise the highest `y` is drawn first.
+    pub fn from_bounds<F: Fn(Point) -> char>(
+        caption: impl Into<String>,
+        bounds: Bounds,
+        y_down: bool,
+        focus: Option<Point>,
+        tile: F
+    ) -> Self {
+        let rows: Vec<i32> = match y_down {
+            true => (bounds.min.y..=bounds.max.y).collect(),
+            false => (bounds.min.y..=bounds.max.y).rev().collect()
+        };
+
+        let lines = rows
+            .iter()
+            .map(|y| (bounds.min.x..=bounds.max.x).map(|x| tile(Point::new(x, *y))).collect())
+            .collect();
+
+        let focus = focus.filter(|p| bounds.contains(*p)).map(|p| {
+            let row = if y_down { p.y - bounds.min.y } else { bounds.max.y - p.y };
+            ((p.x - bounds.min.x) as usize, row as usize)
+        });
+
+        GridFrame::new(caption, lines, focus)
+    }
+}
+
+impl Frame for GridFrame {
+    fn caption(&self) -> String {
+        self.caption.clone()
+    }
+
+    fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    fn focus(&self) -> Option<(usize, usize)> {
+        self.focus
+    }
+}
+
+static RECORDING: AtomicBool = AtomicBool::new(false);
+static FRAMES: Mutex<Vec<Box<dyn Frame>>> = Mutex::new(Vec::new());
+
+pub fn start_recording() {
+    RECORDING.store(true, Ordering::Relaxed);
+}
+
+pub fn is_recording() -> bool {
+    RECORDING.load(Ordering::Relaxed)
+}
+
+/// Records a frame. The frame is only built while recording, so simulations can call this every step.
+pub fn emit<F: Frame + 'static, M: FnOnce() -> F>(make_frame: M) {
+    if !is_recording() {
+        return;
+    }
+
+    FRAMES.lock().unwrap().push(Box::new(make_frame()));
+}
+
+/// Hands over everything recorded so far and starts a fresh recording.
+pub fn take_frames() -> Vec<Box<dyn Frame>> {
+    std::mem::take(&mut *FRAMES.lock().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::{bounds::Bounds, point::Point};
+
+    use super::{Frame, GridFrame};
+
+    #[test]
+    fn grid_frame_draws_rows_in_either_direction() {
+        let bounds = Bounds::new(Point::new(0, 0), Point::new(2, 1));
+        let tile = |p: Point| if p == Point::new(2, 1) { '#' } else { '.' };
+
+        let down = GridFrame::from_bounds("down", bounds, true, Some(Point::new(2, 1)), tile);
+        let up = GridFrame::from_bounds("up", bounds, false, Some(Point::new(2, 1)), tile);
+
+        assert_eq!(down.lines(), &["...".to_string(), "..#".to_string()]);
+        assert_eq!(down.focus(), Some((2, 1)));
+        assert_eq!(up.lines(), &["..#".to_string(), "...".to_string()]);
+        assert_eq!(up.focus(), Some((2, 0)));
+    }
+}
@@ -0,0 +1,341 @@
+//! Writes simulation states to image files when the runner is started with `--export`.
+//! Days draw an `Image` through a `Palette` on their tile enum, and save stills or whole
+//! `Animation`s into the export directory.
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Mutex
+};
+
+use clap::ValueEnum;
+
+use crate::domain::{bounds::Bounds, point::Point};
+
+pub type Rgb = [u8; 3];
+
+/// Colour used for points that have no tile at all.
+pub const VOID: Rgb = [16, 16, 24];
+
+/// How a tile is coloured in an exported image.
+pub trait Palette {
+    fn color(&self) -> Rgb;
+}
+
+impl Palette for Rgb {
+    fn color(&self) -> Rgb {
+        *self
+    }
+}
+
+impl<T: Palette> Palette for &T {
+    fn color(&self) -> Rgb {
+        (*self).color()
+    }
+}
+
+impl<T: Palette> Palette for Option<T> {
+    fn color(&self) -> Rgb {
+        self.as_ref().map(|t| t.color()).unwrap_or(VOID)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ImageFormat {
+    Png,
+    Ppm
+}
+
+impl ImageFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Ppm => "ppm"
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<Rgb>
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize, fill: Rgb) -> Self {
+        return Image { width, height, pixels: vec![fill; width * height] };
+    }
+
+    /// Draws every point in `bounds` as a `scale` x `scale` block coloured by `tile`.
+    /// Rows run top to bottom in increasing `y` when `y_down` is set, like `GridFrame::from_bounds`.
+    pub fn from_bounds<T: Palette, F: Fn(Point) -> T>(bounds: Bounds, y_down: bool, scale: usize, tile: F) -> Self {
+        let scale = scale.max(1);
+        let mut image = Image::new(bounds.width() as usize * scale, bounds.height() as usize * scale, VOID);
+
+        for point in bounds.points() {
+            let column = (point.x - bounds.min.x) as usize;
+            let row = match y_down {
+                true => point.y - bounds.min.y,
+                false => bounds.max.y - point.y
+            } as usize;
+            image.fill_rect(column * scale, row * scale, scale, scale, tile(point).color());
+        }
+
+        return image;
+    }
+
+    pub fn width(&self) -> usize {
+        return self.width;
+    }
+
+    pub fn height(&self) -> usize {
+        return self.height;
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Rgb {
+        return self.pixels[y * self.width + x];
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: Rgb) {
+        if x < self.width && y < self.height {
+            self.pixels[y * self.width + x] = color;
+        }
+    }
+
+    pub fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: Rgb) {
+        for row in y..(y + height).min(self.height) {
+            for column in x..(x + width).min(self.width) {
+                self.pixels[row * self.width + column] = color;
+            }
+        }
+    }
+
+    /// A copy on a larger canvas, with this image centred and the border filled with `fill`.
+    pub fn padded(&self, width: usize, height: usize, fill: Rgb) -> Image {
+        let mut canvas = Image::new(width.max(self.width), height.max(self.height), fill);
+        let left = (canvas.width - self.width) / 2;
+        let top = (canvas.height - self.height) / 2;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                canvas.pixels[(y + top) * canvas.width + x + left] = self.pixel(x, y);
+            }
+        }
+
+        return canvas;
+    }
+
+    fn rgb_bytes(&self) -> Vec<u8> {
+        return self.pixels.iter().flatten().copied().collect();
+    }
+
+    /// The image as a binary (P6) PPM file.
+    pub fn to_ppm(&self) -> Vec<u8> {
+        let mut bytes = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+        bytes.extend(self.rgb_bytes());
+        return bytes;
+    }
+
+    pub fn write_png<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut encoder = png::Encoder::new(writer, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        writer.write_image_data(&self.rgb_bytes()).map_err(io::Error::other)?;
+        return writer.finish().map_err(io::Error::other);
+    }
+
+    pub fn save(&self, path: &Path, format: ImageFormat) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        match format {
+            ImageFormat::Png => self.write_png(&mut file)?,
+            ImageFormat::Ppm => file.write_all(&self.to_ppm())?
+        }
+
+        return file.flush();
+    }
+}
+
+/// A sequence of images played at a fixed frame delay.
+#[derive(Debug, Clone)]
+pub struct Animation {
+    frames: Vec<Image>,
+    delay_ms: u16
+}
+
+impl Animation {
+    pub fn new(delay_ms: u16) -> Self {
+        return Animation { frames: Vec::new(), delay_ms };
+    }
+
+    pub fn push(&mut self, image: Image) {
+        self.frames.push(image);
+    }
+
+    pub fn len(&self) -> usize {
+        return self.frames.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.frames.is_empty();
+    }
+
+    pub fn frames(&self) -> &[Image] {
+        return &self.frames;
+    }
+
+    /// Encodes the frames as a looping GIF. Frames of different sizes are centred on a canvas
+    /// big enough for all of them. GIF palettes hold 256 colours, which is plenty for tile palettes.
+    pub fn write_gif<W: Write>(&self, writer: W) -> io::Result<()> {
+        let width = self.frames.iter().map(|f| f.width).max().unwrap_or(0);
+        let height = self.frames.iter().map(|f| f.height).max().unwrap_or(0);
+        if width > u16::MAX as usize || height > u16::MAX as usize {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Frames are too large for a GIF"));
+        }
+
+        let mut colors: HashMap<Rgb, u8> = HashMap::new();
+        let mut palette = Vec::new();
+        for pixel in self.frames.iter().flat_map(|f| f.pixels.iter()).chain([VOID].iter()) {
+            if colors.contains_key(pixel) {
+                continue;
+            }
+            if colors.len() == 256 {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "GIF frames can only use 256 colours"));
+            }
+            colors.insert(*pixel, colors.len() as u8);
+            palette.extend(pixel);
+        }
+
+        let mut encoder = gif::Encoder::new(writer, width as u16, height as u16, &palette).map_err(io::Error::other)?;
+        encoder.set_repeat(gif::Repeat::Infinite).map_err(io::Error::other)?;
+
+        for image in self.frames.iter() {
+            let image = image.padded(width, height, VOID);
+            let frame = gif::Frame {
+                width: width as u16,
+                height: height as u16,
+                delay: self.delay_ms / 10,
+                buffer: Cow::Owned(image.pixels.iter().map(|p| colors[p]).collect()),
+                ..gif::Frame::default()
+            };
+            encoder.write_frame(&frame).map_err(io::Error::other)?;
+        }
+
+        return Ok(());
+    }
+
+    /// Writes every frame as its own numbered file inside `directory`.
+    pub fn save_sequence(&self, directory: &Path, format: ImageFormat) -> io::Result<()> {
+        fs::create_dir_all(directory)?;
+        for (index, image) in self.frames.iter().enumerate() {
+            image.save(&directory.join(format!("frame_{:04}.{}", index, format.extension())), format)?;
+        }
+
+        return Ok(());
+    }
+}
+
+struct ExportSettings {
+    directory: PathBuf,
+    format: ImageFormat
+}
+
+static SETTINGS: Mutex<Option<ExportSettings>> = Mutex::new(None);
+
+pub fn enable(directory: PathBuf, format: ImageFormat) {
+    *SETTINGS.lock().unwrap() = Some(ExportSettings { directory, format });
+}
+
+pub fn is_enabled() -> bool {
+    SETTINGS.lock().unwrap().is_some()
+}
+
+/// Saves a still as `<name>.png` (or `.ppm`) in the export directory.
+pub fn save_image(name: &str, image: &Image) {
+    write_export(name, |settings| {
+        let path = settings.directory.join(format!("{}.{}", name, settings.format.extension()));
+        image.save(&path, settings.format)?;
+        return Ok(path);
+    });
+}
+
+/// Saves `<name>.gif` plus the individual frames under `<name>/` in the export directory.
+pub fn save_animation(name: &str, animation: &Animation) {
+    if animation.is_empty() {
+        return;
+    }
+
+    write_export(name, |settings| {
+        let path = settings.directory.join(format!("{}.gif", name));
+        animation.write_gif(BufWriter::new(File::create(&path)?))?;
+        animation.save_sequence(&settings.directory.join(name), settings.format)?;
+        return Ok(path);
+    });
+}
+
+fn write_export<F: FnOnce(&ExportSettings) -> io::Result<PathBuf>>(name: &str, write: F) {
+    let settings = SETTINGS.lock().unwrap();
+    let Some(settings) = settings.as_ref() else {
+        return;
+    };
+
+    let result = fs::create_dir_all(&settings.directory).and_then(|_| write(settings));
+    match result {
+        Ok(path) => println!("Exported {}", path.display()),
+        Err(error) => println!("Could not export {}: {}", name, error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::{bounds::Bounds, point::Point};
+
+    use super::{Animation, Image, Palette, Rgb};
+
+    enum Cell {
+        Empty,
+        Full
+    }
+
+    impl Palette for Cell {
+        fn color(&self) -> Rgb {
+            match self {
+                Cell::Empty => [0, 0, 0],
+                Cell::Full => [255, 255, 255]
+            }
+        }
+    }
+
+    #[test]
+    fn image_scales_tiles_and_writes_ppm() {
+        let bounds = Bounds::new(Point::new(0, 0), Point::new(1, 0));
+        let image = Image::from_bounds(bounds, true, 2, |p| if p.x == 1 { Cell::Full } else { Cell::Empty });
+
+        assert_eq!((image.width(), image.height()), (4, 2));
+        assert_eq!(image.pixel(1, 1), [0, 0, 0]);
+        assert_eq!(image.pixel(2, 1), [255, 255, 255]);
+
+        let ppm = image.to_ppm();
+        assert!(ppm.starts_with(b"P6\n4 2\n255\n"));
+        assert_eq!(ppm.len(), 11 + 4 * 2 * 3);
+    }
+
+    #[test]
+    fn png_and_gif_encode_with_their_signatures() {
+        let mut png = Vec::new();
+        Image::new(3, 2, [1, 2, 3]).write_png(&mut png).unwrap();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+
+        let mut animation = Animation::new(100);
+        animation.push(Image::new(2, 2, [255, 0, 0]));
+        animation.push(Image::new(4, 3, [0, 255, 0]));
+        let mut gif = Vec::new();
+        animation.write_gif(&mut gif).unwrap();
+        assert!(gif.starts_with(b"GIF89a"));
+        assert_eq!(&gif[6..10], &[4, 0, 3, 0]);
+    }
+}
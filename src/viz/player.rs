@@ -0,0 +1,166 @@
+use std::{io::{self, Write}, time::Duration};
+
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    execute, queue,
+    style::Print,
+    terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+
+use super::Frame;
+
+const HELP: &str = "space: pause  n/→: step  p/←: back  +/-: speed  q: quit";
+const MIN_DELAY_MS: u64 = 1;
+const MAX_DELAY_MS: u64 = 2000;
+
+struct Playback {
+    index: usize,
+    paused: bool,
+    delay_ms: u64,
+    viewport: (usize, usize)
+}
+
+/// Plays the frames in the terminal until the viewer quits. Playback pauses on the last frame.
+pub fn play(frames: &[Box<dyn Frame>], title: &str) -> crossterm::Result<()> {
+    if frames.is_empty() {
+        return Ok(());
+    }
+
+    let mut stdout = io::stdout();
+    terminal::enable_raw_mode()?;
+    execute!(stdout, EnterAlternateScreen, Hide)?;
+
+    let result = run(&mut stdout, frames, title);
+
+    execute!(stdout, Show, LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+    result
+}
+
+fn run(stdout: &mut io::Stdout, frames: &[Box<dyn Frame>], title: &str) -> crossterm::Result<()> {
+    let mut playback = Playback { index: 0, paused: false, delay_ms: 50, viewport: (0, 0) };
+
+    loop {
+        draw(stdout, frames[playback.index].as_ref(), title, frames.len(), &mut playback)?;
+
+        let timeout = match playback.paused {
+            true => Duration::from_secs(60),
+            false => Duration::from_millis(playback.delay_ms)
+        };
+
+        if event::poll(timeout)? {
+            if let Event::Key(key) = event::read()? {
+                if !handle_key(key, &mut playback, frames.len()) {
+                    return Ok(());
+                }
+            }
+        } else if !playback.paused {
+            if playback.index + 1 < frames.len() {
+                playback.index += 1;
+            } else {
+                playback.paused = true;
+            }
+        }
+    }
+}
+
+// Returns false once the viewer wants to leave.
+fn handle_key(key: KeyEvent, playback: &mut Playback, frame_count: usize) -> bool {
+    match key.code {
+        KeyCode::Char('q') | KeyCode::Esc => return false,
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return false,
+        KeyCode::Char(' ') => playback.paused = !playback.paused,
+        KeyCode::Char('n') | KeyCode::Right => {
+            playback.paused = true;
+            playback.index = (playback.index + 1).min(frame_count - 1);
+        }
+        KeyCode::Char('p') | KeyCode::Left => {
+            playback.paused = true;
+            playback.index = playback.index.saturating_sub(1);
+        }
+        KeyCode::Char('+') | KeyCode::Char('=') | KeyCode::Up => playback.delay_ms = (playback.delay_ms / 2).max(MIN_DELAY_MS),
+        KeyCode::Char('-') | KeyCode::Down => playback.delay_ms = (playback.delay_ms * 2).min(MAX_DELAY_MS),
+        KeyCode::Home => playback.index = 0,
+        KeyCode::End => playback.index = frame_count - 1,
+        _ => ()
+    }
+
+    true
+}
+
+fn draw(stdout: &mut io::Stdout, frame: &dyn Frame, title: &str, frame_count: usize, playback: &mut Playback) -> crossterm::Result<()> {
+    let (columns, rows) = terminal::size()?;
+    let (width, height) = (columns as usize, (rows as usize).saturating_sub(2));
+    let lines = frame.lines();
+
+    playback.viewport = follow(playback.viewport, frame.focus(), (width, height), lines);
+    let (left, top) = playback.viewport;
+
+    queue!(stdout, Clear(ClearType::All))?;
+    let status = format!(
+        "{} | {} | frame {}/{} | {}ms{}",
+        title,
+        frame.caption(),
+        playback.index + 1,
+        frame_count,
+        playback.delay_ms,
+        if playback.paused { " | paused" } else { "" }
+    );
+    queue!(stdout, MoveTo(0, 0), Print(truncate(&status, width)))?;
+
+    for (row, line) in lines.iter().skip(top).take(height).enumerate() {
+        let visible: String = line.chars().skip(left).take(width).collect();
+        queue!(stdout, MoveTo(0, row as u16 + 1), Print(visible))?;
+    }
+
+    queue!(stdout, MoveTo(0, rows.saturating_sub(1)), Print(truncate(HELP, width)))?;
+    stdout.flush()
+}
+
+// Moves the viewport only as far as needed to keep the focus visible, so the picture doesn't jitter.
+fn follow(viewport: (usize, usize), focus: Option<(usize, usize)>, size: (usize, usize), lines: &[String]) -> (usize, usize) {
+    let content_width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+    let content_height = lines.len();
+    let Some((x, y)) = focus else {
+        return (
+            viewport.0.min(content_width.saturating_sub(size.0)),
+            viewport.1.min(content_height.saturating_sub(size.1))
+        );
+    };
+
+    fn axis(start: usize, target: usize, window: usize, content: usize) -> usize {
+        if window == 0 || content <= window {
+            return 0;
+        }
+        let margin = window / 4;
+        let mut start = start;
+        if target < start + margin {
+            start = target.saturating_sub(margin);
+        } else if target + margin >= start + window {
+            start = target + margin + 1 - window;
+        }
+        start.min(content - window)
+    }
+
+    (axis(viewport.0, x, size.0, content_width), axis(viewport.1, y, size.1, content_height))
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    text.chars().take(width).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::follow;
+
+    #[test]
+    fn viewport_follows_the_focus_with_a_margin() {
+        let lines = vec![".".repeat(100); 50];
+
+        assert_eq!(follow((0, 0), Some((10, 10)), (40, 20), &lines), (0, 0));
+        assert_eq!(follow((0, 0), Some((60, 30)), (40, 20), &lines), (31, 16));
+        assert_eq!(follow((31, 16), Some((99, 49)), (40, 20), &lines), (60, 30));
+        assert_eq!(follow((70, 40), None, (40, 20), &lines), (60, 30));
+    }
+}